@@ -1,13 +1,53 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        default_account_state::DefaultAccountState, permanent_delegate::PermanentDelegate,
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    state::AccountState,
+};
+use anchor_spl::token_interface::{
+    burn, close_account, mint_to, Burn, CloseAccount, Mint, MintTo, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+use solana_sha256_hasher::hashv;
 use switchboard_on_demand::accounts::RandomnessAccountData;
 use switchboard_on_demand::{ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID, SWITCHBOARD_PROGRAM_ID};
 
+pub mod merkle;
+
 declare_id!("HrfWNd6ayFHgf23XxLpHtBKY9TfjviiwBpXtdis8MDGU");
 
-/// Platform fee: 10% (1000 basis points)
-const PLATFORM_FEE_BPS: u64 = 1000;
 const BPS_DENOMINATOR: u64 = 10000;
+/// Upper bound the admin can set the platform fee to: 20% (2000 basis points)
+const MAX_PLATFORM_FEE_BPS: u16 = 2000;
+/// Maximum number of mints on the config allowlist
+pub const MAX_ALLOWED_MINTS: usize = 16;
+/// Shortest draw timeout the admin can configure (~5 minutes of slots), past the
+/// ~512-slot window in which Switchboard can still reveal a commit
+const MIN_DRAW_TIMEOUT_SLOTS: u64 = 750;
+/// Maximum number of prize places in a raffle's prize table
+pub const MAX_WINNERS: usize = 10;
+/// Maximum number of prize assets deposited into a prize-asset raffle
+pub const MAX_PRIZE_ASSETS: usize = 5;
+/// Maximum length of a raffle's off-chain metadata URI
+pub const MAX_METADATA_URI_LEN: usize = 200;
+/// Maximum number of tiers in each part of a raffle's pricing schedule
+pub const MAX_PRICE_TIERS: usize = 5;
+/// Maximum number of withdrawn ticket ranges a raffle tracks
+pub const MAX_WITHDRAWN_RANGES: usize = 16;
+/// Smallest withdraw penalty a raffle can set (1%), so filling the withdrawn
+/// ranges to lock other buyers out of withdrawing is never free
+pub const MIN_WITHDRAW_PENALTY_BPS: u16 = 100;
+/// Default time ticket-token holders have after sales close to register their
+/// tokens for the draw (1 day)
+pub const TICKET_REGISTRATION_WINDOW: i64 = 86_400;
+/// Metaplex Token Metadata program, owner of NFT metadata accounts
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+/// `Key::MetadataV1` discriminator of a Metaplex metadata account
+const METADATA_V1_KEY: u8 = 4;
 
 fn is_valid_switchboard_owner(owner: &Pubkey) -> bool {
     owner == &ON_DEMAND_DEVNET_PID
@@ -15,11 +55,677 @@ fn is_valid_switchboard_owner(owner: &Pubkey) -> bool {
         || owner == &SWITCHBOARD_PROGRAM_ID
 }
 
+/// Validate a freshly committed, not-yet-revealed Switchboard randomness account
+/// and return its seed slot
+fn verify_committed_randomness(randomness_account: &AccountInfo, clock: &Clock) -> Result<u64> {
+    require!(
+        is_valid_switchboard_owner(randomness_account.owner),
+        RaffleError::InvalidRandomnessAccountOwner
+    );
+
+    // Parse Switchboard randomness account to verify it's been committed
+    let randomness_data = RandomnessAccountData::parse(randomness_account.data.borrow())
+        .map_err(|_| RaffleError::InvalidRandomnessAccount)?;
+
+    // Verify randomness was committed in this or previous slot (fresh)
+    require!(
+        randomness_data.seed_slot == clock.slot - 1 || randomness_data.seed_slot == clock.slot,
+        RaffleError::RandomnessExpired
+    );
+
+    // Ensure randomness hasn't been revealed yet
+    require!(
+        randomness_data.get_value(clock.slot).is_err(),
+        RaffleError::RandomnessAlreadyRevealed
+    );
+
+    Ok(randomness_data.seed_slot)
+}
+
+/// Before a timed-out draw is recommitted or cancelled, check that its stored
+/// randomness isn't readable right now, so a bad result can't be re-rolled
+fn verify_randomness_unrevealed(
+    raffle: &Raffle,
+    randomness_account: &AccountInfo,
+    clock: &Clock,
+) -> Result<()> {
+    require!(
+        raffle.randomness_account == Some(randomness_account.key()),
+        RaffleError::InvalidRandomnessAccount
+    );
+    require!(
+        is_valid_switchboard_owner(randomness_account.owner),
+        RaffleError::InvalidRandomnessAccountOwner
+    );
+    let randomness_data = RandomnessAccountData::parse(randomness_account.data.borrow())
+        .map_err(|_| RaffleError::InvalidRandomnessAccount)?;
+    require!(
+        randomness_data.get_value(clock.slot).is_err(),
+        RaffleError::RandomnessAlreadyRevealed
+    );
+    Ok(())
+}
+
+/// Derive `count` distinct ticket indices from a single revealed random value,
+/// skipping any index in `exclude`. Each candidate is `sha256(randomness || nonce)
+/// mod total_tickets`; duplicates are skipped by bumping the nonce, so the caller
+/// must ensure `count + exclude.len() <= total_tickets`.
+fn derive_winning_tickets(
+    randomness: &[u8; 32],
+    total_tickets: u32,
+    count: usize,
+    exclude: &[u32],
+) -> Vec<u32> {
+    let mut tickets: Vec<u32> = Vec::with_capacity(count);
+    let mut nonce: u32 = 0;
+    while tickets.len() < count {
+        let hash = hashv(&[randomness.as_ref(), &nonce.to_le_bytes()]).to_bytes();
+        let value = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+        let ticket = (value % total_tickets as u64) as u32;
+        if !tickets.contains(&ticket) && !exclude.contains(&ticket) {
+            tickets.push(ticket);
+        }
+        nonce += 1;
+    }
+    tickets
+}
+
+/// Reject mints whose authorities could freeze or pull the escrow: a freeze
+/// authority, a Token-2022 permanent delegate, or token accounts frozen by default
+fn screen_mint(token_mint: &InterfaceAccount<Mint>) -> Result<()> {
+    // Extensions first: a default-frozen mint always has a freeze authority too
+    screen_mint_extensions(token_mint)?;
+    require!(token_mint.freeze_authority.is_none(), RaffleError::MintHasFreezeAuthority);
+    Ok(())
+}
+
+/// Reject Token-2022 mints with a permanent delegate or token accounts frozen
+/// by default
+fn screen_mint_extensions(token_mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = token_mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = mint_info.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    if let Ok(permanent_delegate) = mint.get_extension::<PermanentDelegate>() {
+        require!(
+            Option::<Pubkey>::from(permanent_delegate.delegate).is_none(),
+            RaffleError::MintHasPermanentDelegate
+        );
+    }
+    if let Ok(default_state) = mint.get_extension::<DefaultAccountState>() {
+        require!(
+            default_state.state != AccountState::Frozen as u8,
+            RaffleError::MintDefaultFrozen
+        );
+    }
+    Ok(())
+}
+
+/// Checks on the price, pot and options shared by `create_raffle` and series templates
+fn validate_raffle_terms(ticket_price: u64, min_pot: u64, options: &RaffleOptions) -> Result<()> {
+    require!(ticket_price > 0, RaffleError::InvalidTicketPrice);
+    require!(min_pot > 0, RaffleError::InvalidMinPot);
+    require!(min_pot >= ticket_price, RaffleError::MinPotTooLow);
+    require!(
+        options.metadata_uri.len() <= MAX_METADATA_URI_LEN,
+        RaffleError::InvalidMetadataUri
+    );
+    let prize_tiers = &options.prize_tiers;
+    require!(
+        !prize_tiers.is_empty() && prize_tiers.len() <= MAX_WINNERS,
+        RaffleError::InvalidPrizeTable
+    );
+    require!(prize_tiers.iter().all(|bps| *bps > 0), RaffleError::InvalidPrizeTable);
+    require!(
+        prize_tiers.iter().map(|bps| *bps as u64).sum::<u64>() == BPS_DENOMINATOR,
+        RaffleError::InvalidPrizeTable
+    );
+    require!(options.claim_window > 0, RaffleError::InvalidClaimWindow);
+    require!(
+        options.max_tickets == 0 || options.max_tickets as usize >= prize_tiers.len(),
+        RaffleError::InvalidMaxTickets
+    );
+    let pricing = &options.pricing;
+    require!(
+        pricing.bulk_tiers.len() <= MAX_PRICE_TIERS
+            && pricing.time_tiers.len() <= MAX_PRICE_TIERS,
+        RaffleError::InvalidPricingSchedule
+    );
+    require!(
+        pricing.bulk_tiers.iter().all(|tier| tier.min_tickets > 1
+            && tier.discount_bps > 0
+            && (tier.discount_bps as u64) < BPS_DENOMINATOR),
+        RaffleError::InvalidPricingSchedule
+    );
+    require!(
+        pricing.time_tiers.iter().all(|tier| tier.seconds_before_end > 0 && tier.markup_bps > 0),
+        RaffleError::InvalidPricingSchedule
+    );
+    if let Some(gate) = &options.holder_gate {
+        require!(gate.min_balance > 0, RaffleError::InvalidHolderGate);
+    }
+    require!(
+        options.withdraw_penalty_bps.is_none_or(|bps| {
+            bps >= MIN_WITHDRAW_PENALTY_BPS && bps as u64 <= BPS_DENOMINATOR
+        }),
+        RaffleError::InvalidWithdrawPenalty
+    );
+    require!(
+        options.registration_window.is_none_or(|window| window > 0),
+        RaffleError::InvalidRegistrationWindow
+    );
+    // Rolling over moves the pot; deposited prize assets have nowhere to go
+    require!(
+        options.rollover_grace.is_none_or(|grace| grace > 0 && options.prize_mode == PrizeMode::Pot),
+        RaffleError::InvalidRollover
+    );
+    // Deposited prize assets all go to a single winner
+    require!(
+        options.prize_mode == PrizeMode::Pot || prize_tiers.len() == 1,
+        RaffleError::InvalidPrizeTable
+    );
+    Ok(())
+}
+
+/// Resolve the pot accounts of a new raffle: SPL raffles pass the mint and
+/// escrow; native SOL raffles pass the vault instead
+fn init_pot(
+    config: &Config,
+    token_mint: &Option<InterfaceAccount<Mint>>,
+    escrow: &Option<InterfaceAccount<TokenAccount>>,
+    vault: &mut Option<Account<SolVault>>,
+    escrow_bump: Option<u8>,
+    vault_bump: Option<u8>,
+) -> Result<(Currency, Pubkey, Pubkey, u8)> {
+    let pot = match (token_mint, escrow, vault.as_mut()) {
+        (Some(token_mint), Some(escrow), None) => {
+            check_mint(config, token_mint)?;
+            let escrow_bump = escrow_bump.ok_or(RaffleError::InvalidPotAccounts)?;
+            (Currency::Spl, token_mint.key(), escrow.key(), escrow_bump)
+        }
+        (None, None, Some(vault)) => {
+            let vault_bump = vault_bump.ok_or(RaffleError::InvalidPotAccounts)?;
+            vault.bump = vault_bump;
+            (Currency::Native, Pubkey::default(), vault.key(), vault_bump)
+        }
+        _ => return err!(RaffleError::InvalidPotAccounts),
+    };
+    Ok(pot)
+}
+
+/// Check a raffle mint against the config allowlist, screening mints the
+/// admin hasn't exempted
+fn check_mint(config: &Config, token_mint: &InterfaceAccount<Mint>) -> Result<()> {
+    require!(
+        config.allowed_mints.is_empty() || config.allowed_mints.contains(&token_mint.key()),
+        RaffleError::MintNotAllowed
+    );
+    if !config.screen_exempt_mints.contains(&token_mint.key()) {
+        screen_mint(token_mint)?;
+    }
+    Ok(())
+}
+
+/// Screen a prize mint like a raffle mint, except that an NFT's freeze
+/// authority may be its Metaplex edition, which only the metadata program controls
+fn check_prize_mint(config: &Config, prize_mint: &InterfaceAccount<Mint>) -> Result<()> {
+    if config.screen_exempt_mints.contains(&prize_mint.key()) {
+        return Ok(());
+    }
+    let (edition, _) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            prize_mint.key().as_ref(),
+            b"edition",
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    if Option::<Pubkey>::from(prize_mint.freeze_authority) == Some(edition) {
+        screen_mint_extensions(prize_mint)
+    } else {
+        screen_mint(prize_mint)
+    }
+}
+
+/// `transfer_checked` that forwards the context's remaining accounts to a
+/// Token-2022 transfer hook. anchor-spl's `transfer_checked` drops them, so the
+/// instruction is built with the hook's extra account metas resolved on-chain.
+fn transfer_checked_with_hook<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Token balance of a token account, read straight from its data so it
+/// reflects CPIs made earlier in the same instruction
+fn token_balance(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    Ok(TokenAccount::try_deserialize(&mut &data[..])?.amount)
+}
+
+/// Verified collection of the NFT `mint`, read from its Metaplex metadata
+/// account. Walks the Borsh layout up to the `collection` field.
+fn verified_collection(metadata: &AccountInfo, mint: &Pubkey) -> Option<Pubkey> {
+    if *metadata.owner != TOKEN_METADATA_PROGRAM_ID {
+        return None;
+    }
+    let data = metadata.try_borrow_data().ok()?;
+    let mut cursor: &[u8] = &data;
+    let key = u8::deserialize(&mut cursor).ok()?;
+    let _update_authority = Pubkey::deserialize(&mut cursor).ok()?;
+    let metadata_mint = Pubkey::deserialize(&mut cursor).ok()?;
+    if key != METADATA_V1_KEY || metadata_mint != *mint {
+        return None;
+    }
+    let _name = String::deserialize(&mut cursor).ok()?;
+    let _symbol = String::deserialize(&mut cursor).ok()?;
+    let _uri = String::deserialize(&mut cursor).ok()?;
+    let _seller_fee_basis_points = u16::deserialize(&mut cursor).ok()?;
+    let _creators = Option::<Vec<(Pubkey, bool, u8)>>::deserialize(&mut cursor).ok()?;
+    let _primary_sale_happened = bool::deserialize(&mut cursor).ok()?;
+    let _is_mutable = bool::deserialize(&mut cursor).ok()?;
+    let _edition_nonce = Option::<u8>::deserialize(&mut cursor).ok()?;
+    let _token_standard = Option::<u8>::deserialize(&mut cursor).ok()?;
+    let (verified, collection) = Option::<(bool, Pubkey)>::deserialize(&mut cursor).ok()??;
+    verified.then_some(collection)
+}
+
+/// Allowlist and holder-gate checks for `beneficiary` entering `raffle`.
+/// Returns the wallet's ticket cap: a non-zero allowance in the allowlist leaf
+/// replaces max_per_wallet for that wallet.
+fn check_eligibility(
+    raffle: &Raffle,
+    beneficiary: &Pubkey,
+    allowlist: Option<AllowlistProof>,
+    holding_token_account: Option<&InterfaceAccount<TokenAccount>>,
+    holding_metadata: Option<&AccountInfo>,
+) -> Result<u32> {
+    // Allowlisted raffles admit only buyers with a valid proof
+    let mut wallet_cap = raffle.max_per_wallet;
+    if let Some(root) = &raffle.allowlist_root {
+        let allowlist = allowlist.ok_or(RaffleError::NotAllowlisted)?;
+        let leaf = merkle::leaf(beneficiary, allowlist.allowance);
+        require!(merkle::verify(root, leaf, &allowlist.proof), RaffleError::NotAllowlisted);
+        if allowlist.allowance > 0 {
+            wallet_cap = allowlist.allowance;
+        }
+    }
+
+    // Holder-gated raffles need a proof-of-holding account at or above the minimum
+    if let Some(gate) = &raffle.holder_gate {
+        let holding = holding_token_account.ok_or(RaffleError::HolderGateFailed)?;
+        require!(holding.owner == *beneficiary, RaffleError::HolderGateFailed);
+        require!(holding.amount >= gate.min_balance, RaffleError::HolderGateFailed);
+        let eligible = match gate.kind {
+            GateKind::Mint => holding.mint == gate.key,
+            GateKind::Collection => holding_metadata
+                .and_then(|metadata| verified_collection(metadata, &holding.mint))
+                == Some(gate.key),
+        };
+        require!(eligible, RaffleError::HolderGateFailed);
+    }
+
+    Ok(wallet_cap)
+}
+
+/// Checks that `next_raffle` can take `batch` carried over from a rolled-over
+/// round: the round is open, has room for the tickets, and admits the batch
+/// owner. Carried tickets were bought already, so the wallet cap doesn't apply.
+fn check_rollover_admission(
+    next_raffle: &Raffle,
+    batch: &TicketBatch,
+    now: i64,
+    allowlist: Option<AllowlistProof>,
+    holding_token_account: Option<&InterfaceAccount<TokenAccount>>,
+    holding_metadata: Option<&AccountInfo>,
+) -> Result<()> {
+    require!(next_raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+    require!(now >= next_raffle.start_time, RaffleError::RaffleNotStarted);
+    require!(now < next_raffle.end_time, RaffleError::RaffleClosed);
+    require!(next_raffle.ticket_mint.is_none(), RaffleError::TicketTokenRaffle);
+    check_eligibility(
+        next_raffle,
+        &batch.buyer,
+        allowlist,
+        holding_token_account,
+        holding_metadata,
+    )?;
+
+    let total_tickets = next_raffle
+        .total_tickets
+        .checked_add(batch.num_tickets)
+        .ok_or(RaffleError::Overflow)?;
+    require!(
+        next_raffle.max_tickets == 0 || total_tickets <= next_raffle.max_tickets,
+        RaffleError::RaffleSoldOut
+    );
+    // Batches that paid nothing were granted
+    if batch.amount_paid == 0 {
+        let granted_tickets = next_raffle
+            .granted_tickets
+            .checked_add(batch.num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        require!(
+            next_raffle.max_granted_tickets == 0
+                || granted_tickets <= next_raffle.max_granted_tickets,
+            RaffleError::GrantCapExceeded
+        );
+    }
+    Ok(())
+}
+
+/// Raffle stored in `raffle_info`, or `None` once the account has been closed
+fn load_raffle(raffle_info: &AccountInfo) -> Option<Raffle> {
+    if *raffle_info.owner != crate::ID {
+        return None;
+    }
+    let data = raffle_info.try_borrow_data().ok()?;
+    Raffle::try_deserialize(&mut &data[..]).ok()
+}
+
+/// The account holding a raffle's pot: the SPL escrow token account, or the
+/// program-owned SOL vault for native raffles
+enum Pot<'a, 'info> {
+    Spl {
+        escrow: &'a InterfaceAccount<'info, TokenAccount>,
+        token_mint: &'a InterfaceAccount<'info, Mint>,
+        token_program: &'a Interface<'info, TokenInterface>,
+        /// Transfer-hook extra accounts, forwarded to every `transfer_checked`
+        hook_accounts: &'a [AccountInfo<'info>],
+        raffle_key: Pubkey,
+        escrow_bump: u8,
+    },
+    Native {
+        vault: &'a Account<'info, SolVault>,
+    },
+}
+
+impl<'a, 'info> Pot<'a, 'info> {
+    /// Pick the pot accounts matching the raffle's currency out of an
+    /// instruction's optional escrow / vault accounts. `hook_accounts` are the
+    /// instruction's remaining accounts: the extra accounts a Token-2022
+    /// transfer-hook mint needs, in the order its hook program expects.
+    fn resolve(
+        raffle: &Account<'info, Raffle>,
+        escrow: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+        token_mint: &'a Option<InterfaceAccount<'info, Mint>>,
+        token_program: &'a Option<Interface<'info, TokenInterface>>,
+        vault: &'a Option<Account<'info, SolVault>>,
+        hook_accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Self> {
+        match raffle.currency {
+            Currency::Spl => match (escrow, token_mint, token_program) {
+                (Some(escrow), Some(token_mint), Some(token_program)) => Ok(Pot::Spl {
+                    escrow,
+                    token_mint,
+                    token_program,
+                    hook_accounts,
+                    raffle_key: raffle.key(),
+                    escrow_bump: raffle.escrow_bump,
+                }),
+                _ => err!(RaffleError::InvalidPotAccounts),
+            },
+            Currency::Native => match vault {
+                Some(vault) => Ok(Pot::Native { vault }),
+                None => err!(RaffleError::InvalidPotAccounts),
+            },
+        }
+    }
+
+    /// Move `amount` from `payer` into the pot and return what the pot actually
+    /// received, which is less than `amount` for a Token-2022 transfer-fee mint.
+    /// SPL raffles debit `from_token_account`.
+    fn collect(
+        &self,
+        from_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<u64> {
+        match self {
+            Pot::Spl { escrow, token_mint, token_program, hook_accounts, .. } => {
+                let from = from_token_account.ok_or(RaffleError::InvalidPotAccounts)?;
+                let escrow_info = escrow.to_account_info();
+                let balance_before = token_balance(&escrow_info)?;
+
+                let cpi_accounts = TransferChecked {
+                    from: from.to_account_info(),
+                    to: escrow_info.clone(),
+                    mint: token_mint.to_account_info(),
+                    authority: payer.clone(),
+                };
+                let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts)
+                    .with_remaining_accounts(hook_accounts.to_vec());
+                transfer_checked_with_hook(cpi_ctx, amount, token_mint.decimals)?;
+
+                let received = token_balance(&escrow_info)?
+                    .checked_sub(balance_before)
+                    .ok_or(RaffleError::Overflow)?;
+                require!(received > 0, RaffleError::NothingReceived);
+                Ok(received)
+            }
+            Pot::Native { vault } => {
+                let cpi_accounts = Transfer {
+                    from: payer.clone(),
+                    to: vault.to_account_info(),
+                };
+                transfer(CpiContext::new(system_program.clone(), cpi_accounts), amount)?;
+                Ok(amount)
+            }
+        }
+    }
+
+    /// Pay `amount` out of the pot. SPL raffles credit `to_token_account`,
+    /// native raffles credit the `to_wallet` lamports directly.
+    fn pay(
+        &self,
+        to_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+        to_wallet: Option<&AccountInfo<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        match self {
+            Pot::Spl { escrow, token_mint, token_program, hook_accounts, raffle_key, escrow_bump } => {
+                let to = to_token_account.ok_or(RaffleError::InvalidPotAccounts)?;
+                let seeds = &[
+                    b"escrow",
+                    raffle_key.as_ref(),
+                    &[*escrow_bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let cpi_accounts = TransferChecked {
+                    from: escrow.to_account_info(),
+                    to: to.to_account_info(),
+                    mint: token_mint.to_account_info(),
+                    authority: escrow.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    cpi_accounts,
+                    signer,
+                )
+                .with_remaining_accounts(hook_accounts.to_vec());
+                transfer_checked_with_hook(cpi_ctx, amount, token_mint.decimals)
+            }
+            Pot::Native { vault } => {
+                let to_wallet = to_wallet.ok_or(RaffleError::InvalidPotAccounts)?;
+                vault.to_account_info().sub_lamports(amount)?;
+                to_wallet.add_lamports(amount)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Balance held by the pot, excluding the vault's own rent
+    fn balance(&self) -> Result<u64> {
+        match self {
+            Pot::Spl { escrow, .. } => Ok(escrow.amount),
+            Pot::Native { vault } => {
+                let info = vault.to_account_info();
+                let rent = Rent::get()?.minimum_balance(info.data_len());
+                Ok(info.lamports().saturating_sub(rent))
+            }
+        }
+    }
+}
+
 #[program]
 pub mod rafflebot {
     use super::*;
 
-    /// Create a new raffle (agent-only in practice, but permissionless on-chain)
+    /// Initialize the singleton protocol config (program upgrade authority only)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        treasury: Pubkey,
+        fee_bps: u16,
+        draw_timeout_slots: u64,
+        keeper_reward_bps: u16,
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_PLATFORM_FEE_BPS, RaffleError::InvalidFee);
+        require!(keeper_reward_bps <= fee_bps, RaffleError::InvalidKeeperReward);
+        require!(draw_timeout_slots >= MIN_DRAW_TIMEOUT_SLOTS, RaffleError::InvalidDrawTimeout);
+
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.treasury = treasury;
+        config.fee_bps = fee_bps;
+        config.draw_timeout_slots = draw_timeout_slots;
+        config.keeper_reward_bps = keeper_reward_bps;
+        config.allowed_mints = Vec::new();
+        config.screen_exempt_mints = Vec::new();
+        config.bump = ctx.bumps.config;
+
+        msg!("Config initialized | Admin: {} | Treasury: {} | Fee: {} bps", 
+            config.admin, treasury, fee_bps);
+
+        Ok(())
+    }
+
+    /// Update protocol config (admin only). `None` leaves a field unchanged.
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        new_admin: Option<Pubkey>,
+        treasury: Option<Pubkey>,
+        fee_bps: Option<u16>,
+        draw_timeout_slots: Option<u64>,
+        keeper_reward_bps: Option<u16>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        if let Some(fee_bps) = fee_bps {
+            require!(fee_bps <= MAX_PLATFORM_FEE_BPS, RaffleError::InvalidFee);
+            config.fee_bps = fee_bps;
+        }
+        if let Some(draw_timeout_slots) = draw_timeout_slots {
+            require!(
+                draw_timeout_slots >= MIN_DRAW_TIMEOUT_SLOTS,
+                RaffleError::InvalidDrawTimeout
+            );
+            config.draw_timeout_slots = draw_timeout_slots;
+        }
+        if let Some(keeper_reward_bps) = keeper_reward_bps {
+            config.keeper_reward_bps = keeper_reward_bps;
+        }
+        require!(config.keeper_reward_bps <= config.fee_bps, RaffleError::InvalidKeeperReward);
+        if let Some(treasury) = treasury {
+            config.treasury = treasury;
+        }
+        if let Some(new_admin) = new_admin {
+            config.admin = new_admin;
+        }
+
+        msg!("Config updated | Admin: {} | Treasury: {} | Fee: {} bps", 
+            config.admin, config.treasury, config.fee_bps);
+
+        Ok(())
+    }
+
+    /// Add a mint to the allowlist (admin only). An empty allowlist accepts any mint
+    /// that passes the mint safety screen.
+    pub fn add_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(!config.allowed_mints.contains(&mint), RaffleError::MintAlreadyAllowed);
+        require!(config.allowed_mints.len() < MAX_ALLOWED_MINTS, RaffleError::AllowlistFull);
+        config.allowed_mints.push(mint);
+
+        msg!("Mint allowed: {}", mint);
+
+        Ok(())
+    }
+
+    /// Remove a mint from the allowlist (admin only)
+    pub fn remove_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        let index = config
+            .allowed_mints
+            .iter()
+            .position(|allowed| allowed == &mint)
+            .ok_or(RaffleError::MintNotAllowed)?;
+        config.allowed_mints.remove(index);
+
+        msg!("Mint removed from allowlist: {}", mint);
+
+        Ok(())
+    }
+
+    /// Exempt a mint from the mint safety screen (admin only), for vetted mints
+    /// that keep a freeze authority or permanent delegate, such as USDC
+    pub fn add_screen_exempt_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        require!(!config.screen_exempt_mints.contains(&mint), RaffleError::MintAlreadyExempt);
+        require!(
+            config.screen_exempt_mints.len() < MAX_ALLOWED_MINTS,
+            RaffleError::ScreenExemptionsFull
+        );
+        config.screen_exempt_mints.push(mint);
+
+        msg!("Mint exempt from screening: {}", mint);
+
+        Ok(())
+    }
+
+    /// Remove a mint's screening exemption (admin only)
+    pub fn remove_screen_exempt_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        let index = config
+            .screen_exempt_mints
+            .iter()
+            .position(|exempt| exempt == &mint)
+            .ok_or(RaffleError::MintNotExempt)?;
+        config.screen_exempt_mints.remove(index);
+
+        msg!("Mint screening exemption removed: {}", mint);
+
+        Ok(())
+    }
+
+    /// Create a new raffle (agent-only in practice, but permissionless on-chain).
+    /// Passing `ticket_mint` sells ticket tokens instead of entries. Their holders
+    /// register them after sales close; payments for tokens not registered within
+    /// the registration window stay in the pot for the winners and fees.
     pub fn create_raffle(
         ctx: Context<CreateRaffle>,
         name: String,
@@ -27,90 +733,456 @@ pub mod rafflebot {
         min_pot: u64,
         max_per_wallet: u32,
         end_time: i64,
+        options: RaffleOptions,
     ) -> Result<()> {
         let raffle = &mut ctx.accounts.raffle;
+        let config = &ctx.accounts.config;
         let clock = Clock::get()?;
 
         // Validations
         require!(end_time > clock.unix_timestamp, RaffleError::InvalidEndTime);
-        require!(ticket_price > 0, RaffleError::InvalidTicketPrice);
-        require!(min_pot > 0, RaffleError::InvalidMinPot);
-        require!(min_pot >= ticket_price, RaffleError::MinPotTooLow);
-        require!(name.len() > 0 && name.len() <= 32, RaffleError::InvalidName);
+        let start_time = options.start_time.unwrap_or(clock.unix_timestamp);
+        require!(start_time < end_time, RaffleError::InvalidStartTime);
+        if let Some(soft_close) = &options.soft_close {
+            require!(
+                soft_close.window > 0
+                    && soft_close.extension > 0
+                    && soft_close.max_end_time >= end_time,
+                RaffleError::InvalidSoftClose
+            );
+        }
+        require!(!name.is_empty() && name.len() <= 32, RaffleError::InvalidName);
+        // Only series rounds have a next raffle to roll into
+        require!(options.rollover_grace.is_none(), RaffleError::InvalidRollover);
+        validate_raffle_terms(ticket_price, min_pot, &options)?;
 
+        let (currency, token_mint, escrow, escrow_bump) = init_pot(
+            config,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.escrow,
+            &mut ctx.accounts.vault,
+            ctx.bumps.escrow,
+            ctx.bumps.vault,
+        )?;
+
+        raffle.init_state(options, config, clock.unix_timestamp);
         raffle.authority = ctx.accounts.authority.key();
         raffle.name = name;
-        raffle.token_mint = ctx.accounts.token_mint.key();
-        raffle.escrow = ctx.accounts.escrow.key();
+        raffle.series = None;
+        raffle.round = 0;
+        raffle.currency = currency;
+        raffle.token_mint = token_mint;
+        raffle.escrow = escrow;
         raffle.ticket_price = ticket_price;
         raffle.min_pot = min_pot;
         raffle.max_per_wallet = max_per_wallet;
+        raffle.start_time = start_time;
         raffle.end_time = end_time;
-        raffle.total_tickets = 0;
-        raffle.total_pot = 0;
-        raffle.status = RaffleStatus::Active;
-        raffle.winner = None;
-        raffle.winning_ticket = None;
-        raffle.randomness = None;
-        raffle.randomness_account = None;
-        raffle.commit_slot = None;
-        raffle.platform_wallet = ctx.accounts.platform_wallet.key();
-        raffle.created_at = clock.unix_timestamp;
+        raffle.ticket_mint = ctx.accounts.ticket_mint.as_ref().map(|mint| mint.key());
+        raffle.ticket_mint_bump = ctx.bumps.ticket_mint.unwrap_or_default();
         raffle.bump = ctx.bumps.raffle;
-        raffle.escrow_bump = ctx.bumps.escrow;
+        raffle.escrow_bump = escrow_bump;
 
-        msg!("Raffle created: {} | Ticket: {} | Min pot: {}", 
-            raffle.name, ticket_price, min_pot);
+        msg!("Raffle created: {} | Currency: {:?} | Ticket: {} | Min pot: {} | Winners: {}", 
+            raffle.name, currency, ticket_price, min_pot, raffle.prizes.len());
         
         Ok(())
     }
 
-    /// Buy tickets for a raffle
-    pub fn buy_tickets(ctx: Context<BuyTickets>, num_tickets: u32) -> Result<()> {
+    /// Create a recurring raffle series. Each round is a raffle opened with
+    /// `open_round` from this template, running `duration` seconds from when
+    /// it opens. Templates can't set `start_time`, and `soft_close.max_end_time`
+    /// is an offset past each round's `end_time`.
+    pub fn create_series(
+        ctx: Context<CreateSeries>,
+        name: String,
+        ticket_price: u64,
+        min_pot: u64,
+        max_per_wallet: u32,
+        duration: i64,
+        options: RaffleOptions,
+    ) -> Result<()> {
+        require!(!name.is_empty() && name.len() <= 32, RaffleError::InvalidName);
+        require!(duration > 0, RaffleError::InvalidEndTime);
+        require!(options.start_time.is_none(), RaffleError::InvalidStartTime);
+        if let Some(soft_close) = &options.soft_close {
+            require!(
+                soft_close.window > 0 && soft_close.extension > 0 && soft_close.max_end_time >= 0,
+                RaffleError::InvalidSoftClose
+            );
+        }
+        validate_raffle_terms(ticket_price, min_pot, &options)?;
+        let (currency, token_mint) = match &ctx.accounts.token_mint {
+            Some(token_mint) => {
+                check_mint(&ctx.accounts.config, token_mint)?;
+                (Currency::Spl, token_mint.key())
+            }
+            None => (Currency::Native, Pubkey::default()),
+        };
+
+        let series = &mut ctx.accounts.series;
+        series.authority = ctx.accounts.authority.key();
+        series.name = name;
+        series.currency = currency;
+        series.token_mint = token_mint;
+        series.ticket_price = ticket_price;
+        series.min_pot = min_pot;
+        series.max_per_wallet = max_per_wallet;
+        series.duration = duration;
+        series.options = options;
+        series.round = 0;
+        series.current_raffle = None;
+        series.bump = ctx.bumps.series;
+
+        msg!("Series created: {} | Currency: {:?} | Ticket: {} | Min pot: {} | Duration: {}", 
+            series.name, currency, ticket_price, min_pot, duration);
+
+        Ok(())
+    }
+
+    /// Open the next round of a series (authority only). The previous round
+    /// must have settled its draw, been cancelled or rolled over, or been closed.
+    pub fn open_round(ctx: Context<OpenRound>) -> Result<()> {
+        let series = &ctx.accounts.series;
+        let config = &ctx.accounts.config;
+        let clock = Clock::get()?;
+
+        if series.current_raffle.is_some() {
+            let previous = &ctx.accounts.previous_raffle;
+            // A closed round has already finished
+            if !previous.data_is_empty() {
+                let data = previous.try_borrow_data()?;
+                let previous_raffle = Raffle::try_deserialize(&mut &data[..])?;
+                require!(
+                    matches!(
+                        previous_raffle.status,
+                        RaffleStatus::DrawComplete
+                            | RaffleStatus::Claimed
+                            | RaffleStatus::Swept
+                            | RaffleStatus::Cancelled
+                            | RaffleStatus::RolledOver
+                    ),
+                    RaffleError::RoundStillOpen
+                );
+            }
+        }
+
+        let (currency, token_mint, escrow, escrow_bump) = init_pot(
+            config,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.escrow,
+            &mut ctx.accounts.vault,
+            ctx.bumps.escrow,
+            ctx.bumps.vault,
+        )?;
+        require!(currency == series.currency, RaffleError::InvalidPotAccounts);
+
+        let round = series.round.checked_add(1).ok_or(RaffleError::Overflow)?;
+        let end_time = clock
+            .unix_timestamp
+            .checked_add(series.duration)
+            .ok_or(RaffleError::Overflow)?;
+
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.init_state(series.options.clone(), config, clock.unix_timestamp);
+        raffle.authority = series.authority;
+        raffle.name = series.name.clone();
+        raffle.series = Some(series.key());
+        raffle.round = round;
+        raffle.currency = currency;
+        raffle.token_mint = token_mint;
+        raffle.escrow = escrow;
+        raffle.ticket_price = series.ticket_price;
+        raffle.min_pot = series.min_pot;
+        raffle.max_per_wallet = series.max_per_wallet;
+        raffle.start_time = clock.unix_timestamp;
+        raffle.end_time = end_time;
+        if let Some(soft_close) = &series.options.soft_close {
+            raffle.soft_close = Some(SoftClose {
+                window: soft_close.window,
+                extension: soft_close.extension,
+                max_end_time: end_time
+                    .checked_add(soft_close.max_end_time)
+                    .ok_or(RaffleError::Overflow)?,
+            });
+        }
+        raffle.ticket_mint = ctx.accounts.ticket_mint.as_ref().map(|mint| mint.key());
+        raffle.ticket_mint_bump = ctx.bumps.ticket_mint.unwrap_or_default();
+        raffle.bump = ctx.bumps.raffle;
+        raffle.escrow_bump = escrow_bump;
+
+        let raffle_key = raffle.key();
+        let series = &mut ctx.accounts.series;
+        series.round = round;
+        series.current_raffle = Some(raffle_key);
+
+        msg!("Round opened: {} #{} | Raffle: {} | Ends: {}", 
+            series.name, round, raffle_key, end_time);
+
+        Ok(())
+    }
+
+    /// Deposit a prize asset (an NFT, or a fixed amount of any SPL mint) into its
+    /// prize vault (authority only, before any ticket is sold). Bundle with
+    /// `create_raffle` so buyers always see the full prize.
+    pub fn deposit_prize<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositPrize<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+
+        require!(raffle.prize_mode == PrizeMode::Assets, RaffleError::NotPrizeAssetRaffle);
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(raffle.total_tickets == 0, RaffleError::TicketsAlreadySold);
+        require!(amount > 0, RaffleError::InvalidPrizeAmount);
+        require!(raffle.prize_assets.len() < MAX_PRIZE_ASSETS, RaffleError::TooManyPrizeAssets);
+        check_prize_mint(&ctx.accounts.config, &ctx.accounts.prize_mint)?;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.authority_token_account.to_account_info(),
+            to: ctx.accounts.prize_vault.to_account_info(),
+            mint: ctx.accounts.prize_mint.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts)
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_checked_with_hook(cpi_ctx, amount, ctx.accounts.prize_mint.decimals)?;
+
+        // Record what the vault actually holds (net of any transfer fee)
+        let received = token_balance(&ctx.accounts.prize_vault.to_account_info())?;
+        raffle.prize_assets.push(PrizeAsset {
+            mint: ctx.accounts.prize_mint.key(),
+            amount: received,
+            vault_bump: ctx.bumps.prize_vault,
+            released: false,
+        });
+
+        msg!("Prize deposited: {} | Mint: {} | Amount: {}", 
+            raffle.name, ctx.accounts.prize_mint.key(), received);
+
+        Ok(())
+    }
+
+    /// Edit raffle parameters (authority only, while sales are open). Before the
+    /// first ticket is sold anything may change; afterwards only buyer-friendly
+    /// edits are accepted: a later `end_time` or a lower `min_pot`.
+    pub fn update_raffle(
+        ctx: Context<UpdateRaffle>,
+        ticket_price: Option<u64>,
+        min_pot: Option<u64>,
+        max_per_wallet: Option<u32>,
+        end_time: Option<i64>,
+        metadata_uri: Option<String>,
+    ) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(clock.unix_timestamp < raffle.end_time, RaffleError::RaffleClosed);
+
+        let sales_started = raffle.total_tickets > 0;
+
+        if let Some(ticket_price) = ticket_price {
+            require!(!sales_started, RaffleError::RaffleUpdateLocked);
+            require!(ticket_price > 0, RaffleError::InvalidTicketPrice);
+            raffle.ticket_price = ticket_price;
+        }
+        if let Some(max_per_wallet) = max_per_wallet {
+            require!(!sales_started, RaffleError::RaffleUpdateLocked);
+            raffle.max_per_wallet = max_per_wallet;
+        }
+        if let Some(metadata_uri) = metadata_uri {
+            require!(!sales_started, RaffleError::RaffleUpdateLocked);
+            require!(
+                metadata_uri.len() <= MAX_METADATA_URI_LEN,
+                RaffleError::InvalidMetadataUri
+            );
+            raffle.metadata_uri = metadata_uri;
+        }
+        if let Some(min_pot) = min_pot {
+            require!(
+                !sales_started || min_pot <= raffle.min_pot,
+                RaffleError::RaffleUpdateLocked
+            );
+            raffle.min_pot = min_pot;
+        }
+        if let Some(end_time) = end_time {
+            require!(
+                !sales_started || end_time >= raffle.end_time,
+                RaffleError::RaffleUpdateLocked
+            );
+            require!(end_time > clock.unix_timestamp, RaffleError::InvalidEndTime);
+            require!(raffle.start_time < end_time, RaffleError::InvalidStartTime);
+            if let Some(soft_close) = &raffle.soft_close {
+                require!(soft_close.max_end_time >= end_time, RaffleError::InvalidSoftClose);
+            }
+            raffle.end_time = end_time;
+        }
+
+        // Re-check the pricing invariants `create_raffle` enforces
+        require!(raffle.min_pot > 0, RaffleError::InvalidMinPot);
+        require!(raffle.min_pot >= raffle.ticket_price, RaffleError::MinPotTooLow);
+
+        msg!("Raffle updated: {} | Ticket: {} | Min pot: {} | End: {}", 
+            raffle.name, raffle.ticket_price, raffle.min_pot, raffle.end_time);
+
+        Ok(())
+    }
+
+    /// Buy tickets for a raffle. Every purchase records its own `TicketBatch`
+    /// so repeat buys by the same wallet never overlap other buyers' tickets.
+    /// A purchase past the `max_tickets` supply cap is partially filled with
+    /// whatever tickets remain.
+    /// For a Token-2022 transfer-hook mint, pass the hook's extra accounts as
+    /// remaining accounts (likewise on every instruction that pays out).
+    /// Allowlisted raffles require a Merkle proof of the beneficiary's leaf.
+    /// The `buyer` signer pays; the `beneficiary` (the buyer itself, or a wallet
+    /// being gifted tickets) owns the entry and any prize. `refund_to_payer`
+    /// picks who this purchase's refund goes to; it is recorded on the batch.
+    /// Ticket-token raffles mint the tickets to the beneficiary's ticket account
+    /// instead of recording a batch; see `register_tickets`.
+    /// The purchase fails if it would cost more than `max_total_cost`.
+    pub fn buy_tickets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTickets<'info>>,
+        requested_tickets: u32,
+        max_total_cost: u64,
+        allowlist: Option<AllowlistProof>,
+        refund_to_payer: bool,
+    ) -> Result<()> {
         let raffle = &mut ctx.accounts.raffle;
         let clock = Clock::get()?;
 
         // Validations
         require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(clock.unix_timestamp >= raffle.start_time, RaffleError::RaffleNotStarted);
         require!(clock.unix_timestamp < raffle.end_time, RaffleError::RaffleClosed);
-        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
+        require!(requested_tickets > 0, RaffleError::InvalidTicketCount);
+        require!(!raffle.sold_out(), RaffleError::RaffleSoldOut);
 
-        // Check max per wallet
+        // Partially fill past the supply cap
+        let num_tickets = match raffle.max_tickets {
+            0 => requested_tickets,
+            max_tickets => requested_tickets.min(max_tickets - raffle.total_tickets),
+        };
+
+        let wallet_cap = check_eligibility(
+            raffle,
+            &ctx.accounts.beneficiary.key(),
+            allowlist,
+            ctx.accounts.holding_token_account.as_ref(),
+            ctx.accounts.holding_metadata.as_deref(),
+        )?;
+
+        // Check max per wallet; granted tickets don't count against it
         let entry = &ctx.accounts.entry;
-        let current_tickets = if entry.is_initialized { entry.num_tickets } else { 0 };
+        let current_tickets = if entry.is_initialized {
+            entry.num_tickets - entry.granted_tickets
+        } else {
+            0
+        };
         
-        if raffle.max_per_wallet > 0 {
+        if wallet_cap > 0 {
             require!(
-                current_tickets + num_tickets <= raffle.max_per_wallet,
+                current_tickets + num_tickets <= wallet_cap,
                 RaffleError::MaxTicketsExceeded
             );
         }
 
-        // Calculate cost
-        let total_cost = raffle.ticket_price
-            .checked_mul(num_tickets as u64)
-            .ok_or(RaffleError::Overflow)?;
+        // Calculate cost under the pricing schedule
+        let total_cost = raffle.ticket_cost(num_tickets, clock.unix_timestamp)?;
+        require!(total_cost > 0, RaffleError::InvalidPricingSchedule);
+        require!(total_cost <= max_total_cost, RaffleError::CostExceedsMax);
 
-        // Transfer tokens from buyer to escrow
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.buyer_token_account.to_account_info(),
-            to: ctx.accounts.escrow.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            authority: ctx.accounts.buyer.to_account_info(),
+        // Transfer payment from buyer to the pot. The pot is credited with what
+        // it actually received, net of any Token-2022 transfer fee.
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        let received = pot.collect(
+            ctx.accounts.buyer_token_account.as_ref(),
+            ctx.accounts.buyer.as_ref(),
+            &ctx.accounts.system_program.to_account_info(),
+            total_cost,
+        )?;
+
+        // Record this purchase's ticket range, or mint ticket tokens whose
+        // ranges are assigned when they are registered for the draw
+        let refund_recipient = if refund_to_payer {
+            ctx.accounts.buyer.key()
+        } else {
+            ctx.accounts.beneficiary.key()
+        };
+        let batch_index = match (raffle.ticket_mint, ctx.accounts.batch.as_mut()) {
+            (None, Some(batch)) => {
+                batch.raffle = raffle.key();
+                batch.buyer = ctx.accounts.beneficiary.key();
+                batch.index = raffle.num_batches;
+                batch.start_ticket_index = raffle.next_ticket_index()?;
+                batch.num_tickets = num_tickets;
+                batch.amount_paid = received;
+                batch.refund_recipient = refund_recipient;
+                batch.refunded = false;
+                batch.bump = ctx.bumps.batch.ok_or(RaffleError::InvalidTicketAccounts)?;
+                Some(batch.index)
+            }
+            (Some(_), None) => {
+                let (Some(ticket_mint), Some(ticket_account), Some(token_program)) = (
+                    &ctx.accounts.ticket_mint,
+                    &ctx.accounts.beneficiary_ticket_account,
+                    &ctx.accounts.token_program,
+                ) else {
+                    return err!(RaffleError::InvalidTicketAccounts);
+                };
+                let raffle_key = raffle.key();
+                let seeds = &[b"ticket_mint".as_ref(), raffle_key.as_ref(), &[raffle.ticket_mint_bump]];
+                let signer_seeds = &[&seeds[..]];
+                let cpi_accounts = MintTo {
+                    mint: ticket_mint.to_account_info(),
+                    to: ticket_account.to_account_info(),
+                    authority: ticket_mint.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                mint_to(cpi_ctx, num_tickets as u64)?;
+                None
+            }
+            _ => return err!(RaffleError::InvalidTicketAccounts),
         };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        transfer_checked(cpi_ctx, total_cost, ctx.accounts.token_mint.decimals)?;
 
         // Update or initialize entry
         let entry = &mut ctx.accounts.entry;
         if !entry.is_initialized {
             entry.raffle = raffle.key();
-            entry.buyer = ctx.accounts.buyer.key();
-            entry.start_ticket_index = raffle.total_tickets;
+            entry.buyer = ctx.accounts.beneficiary.key();
             entry.num_tickets = 0;
+            entry.granted_tickets = 0;
+            entry.registered_tickets = 0;
+            entry.num_batches = 0;
             entry.is_initialized = true;
-            entry.refunded = false;
             entry.bump = ctx.bumps.entry;
+            raffle.num_entries = raffle
+                .num_entries
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+        }
+
+        if batch_index.is_some() {
+            entry.num_batches = entry
+                .num_batches
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+            raffle.num_batches = raffle
+                .num_batches
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
         }
 
         entry.num_tickets = entry
@@ -123,62 +1195,470 @@ pub mod rafflebot {
             .ok_or(RaffleError::Overflow)?;
         raffle.total_pot = raffle
             .total_pot
-            .checked_add(total_cost)
+            .checked_add(received)
             .ok_or(RaffleError::Overflow)?;
 
-        msg!("{} bought {} tickets | Total: {} | Pot: {}", 
-            ctx.accounts.buyer.key(), num_tickets, raffle.total_tickets, raffle.total_pot);
+        // Soft close: a late buy pushes the deadline out, up to the hard maximum
+        if let Some(soft_close) = &raffle.soft_close {
+            let in_window = raffle.end_time - clock.unix_timestamp <= soft_close.window;
+            if in_window && raffle.end_time < soft_close.max_end_time {
+                let previous_end_time = raffle.end_time;
+                raffle.end_time = raffle
+                    .end_time
+                    .checked_add(soft_close.extension)
+                    .ok_or(RaffleError::Overflow)?
+                    .min(soft_close.max_end_time);
+                raffle.num_extensions = raffle
+                    .num_extensions
+                    .checked_add(1)
+                    .ok_or(RaffleError::Overflow)?;
+
+                msg!("End time extended | From: {} | To: {} | Extensions: {}", 
+                    previous_end_time, raffle.end_time, raffle.num_extensions);
+            }
+        }
+
+        msg!("{} bought {}/{} tickets | Payer: {} | Batch: {:?} | Paid: {} | Received: {} | Total: {} | Pot: {}", 
+            ctx.accounts.beneficiary.key(), num_tickets, requested_tickets,
+            ctx.accounts.buyer.key(), batch_index, total_cost, received, raffle.total_tickets,
+            raffle.total_pot);
 
         Ok(())
     }
 
-    /// Phase 1: Commit to Switchboard randomness (authority calls after deadline)
-    /// Bundle this instruction with Switchboard's commitIx in the same tx
-    pub fn commit_draw(ctx: Context<CommitDraw>) -> Result<()> {
+    /// Grant free tickets to a recipient (authority only, while sales are open).
+    /// Granted tickets take part in the draw like bought ones but add nothing to
+    /// the pot, so they are never refunded. Capped by `max_granted_tickets`.
+    pub fn grant_tickets(ctx: Context<GrantTickets>, num_tickets: u32) -> Result<()> {
         let raffle = &mut ctx.accounts.raffle;
         let clock = Clock::get()?;
 
-        // Validations
         require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
-        require!(clock.unix_timestamp >= raffle.end_time, RaffleError::RaffleNotEnded);
-        require!(raffle.total_tickets > 0, RaffleError::NoTickets);
-        require!(raffle.total_pot >= raffle.min_pot, RaffleError::ThresholdNotMet);
-        require!(
-            is_valid_switchboard_owner(ctx.accounts.randomness_account.owner),
-            RaffleError::InvalidRandomnessAccountOwner
-        );
-
-        // Parse Switchboard randomness account to verify it's been committed
-        let randomness_data = RandomnessAccountData::parse(
-            ctx.accounts.randomness_account.data.borrow()
-        ).map_err(|_| RaffleError::InvalidRandomnessAccount)?;
+        require!(raffle.ticket_mint.is_none(), RaffleError::TicketTokenRaffle);
+        require!(clock.unix_timestamp < raffle.end_time, RaffleError::RaffleClosed);
+        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
 
-        // Verify randomness was committed in this or previous slot (fresh)
+        let granted_tickets = raffle
+            .granted_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
         require!(
-            randomness_data.seed_slot == clock.slot - 1 || randomness_data.seed_slot == clock.slot,
-            RaffleError::RandomnessExpired
+            raffle.max_granted_tickets == 0 || granted_tickets <= raffle.max_granted_tickets,
+            RaffleError::GrantCapExceeded
         );
-
-        // Ensure randomness hasn't been revealed yet
+        let total_tickets = raffle
+            .total_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
         require!(
-            randomness_data.get_value(clock.slot).is_err(),
-            RaffleError::RandomnessAlreadyRevealed
+            raffle.max_tickets == 0 || total_tickets <= raffle.max_tickets,
+            RaffleError::RaffleSoldOut
         );
 
-        // Store the randomness account and commit slot
-        raffle.randomness_account = Some(ctx.accounts.randomness_account.key());
-        raffle.commit_slot = Some(randomness_data.seed_slot);
-        raffle.status = RaffleStatus::DrawCommitted;
+        // Record the granted range like a purchase
+        let batch = &mut ctx.accounts.batch;
+        batch.raffle = raffle.key();
+        batch.buyer = ctx.accounts.recipient.key();
+        batch.index = raffle.num_batches;
+        batch.start_ticket_index = raffle.next_ticket_index()?;
+        batch.num_tickets = num_tickets;
+        batch.amount_paid = 0;
+        batch.refund_recipient = ctx.accounts.recipient.key();
+        batch.refunded = false;
+        batch.bump = ctx.bumps.batch;
 
-        msg!("Draw committed! Randomness account: {} | Seed slot: {}", 
-            ctx.accounts.randomness_account.key(), randomness_data.seed_slot);
+        let entry = &mut ctx.accounts.entry;
+        if !entry.is_initialized {
+            entry.raffle = raffle.key();
+            entry.buyer = ctx.accounts.recipient.key();
+            entry.num_tickets = 0;
+            entry.granted_tickets = 0;
+            entry.registered_tickets = 0;
+            entry.num_batches = 0;
+            entry.is_initialized = true;
+            entry.bump = ctx.bumps.entry;
+            raffle.num_entries = raffle
+                .num_entries
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+        }
 
-        Ok(())
+        entry.num_batches = entry
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+        entry.num_tickets = entry
+            .num_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        entry.granted_tickets = entry
+            .granted_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        raffle.granted_tickets = granted_tickets;
+        raffle.total_tickets = total_tickets;
+        raffle.num_batches = raffle
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+
+        msg!("{} granted {} tickets | Batch: {} | Granted: {} | Total: {}", 
+            ctx.accounts.recipient.key(), num_tickets, batch.index, raffle.granted_tickets,
+            raffle.total_tickets);
+
+        Ok(())
+    }
+
+    /// Move `num_tickets` from the end of one of the sender's batches to another
+    /// wallet while the raffle is `Active`. The moved range becomes a new batch
+    /// owned by the receiver, so every ticket index still maps to exactly one
+    /// owner for prize claims. What the moved tickets paid moves with them, so
+    /// a later refund or withdrawal values them the same, but refunds still go
+    /// to whoever paid. Not available on gated raffles.
+    pub fn transfer_tickets(ctx: Context<TransferTickets>, num_tickets: u32) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(raffle.ticket_mint.is_none(), RaffleError::TicketTokenRaffle);
+        require!(
+            raffle.allowlist_root.is_none() && raffle.holder_gate.is_none(),
+            RaffleError::TicketsNotTransferable
+        );
+        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
+
+        // Split the tail of the source batch off into the receiver's new batch
+        let batch = &mut ctx.accounts.batch;
+        let kept_tickets = batch
+            .num_tickets
+            .checked_sub(num_tickets)
+            .ok_or(RaffleError::InvalidTicketCount)?;
+        let moved_amount = batch.paid_for(num_tickets)?;
+        // Tickets of a granted batch stay granted with the receiver
+        let moved_granted = if batch.amount_paid == 0 { num_tickets } else { 0 };
+        batch.num_tickets = kept_tickets;
+        batch.amount_paid -= moved_amount;
+
+        let new_batch = &mut ctx.accounts.new_batch;
+        new_batch.raffle = raffle.key();
+        new_batch.buyer = ctx.accounts.receiver.key();
+        new_batch.index = raffle.num_batches;
+        new_batch.start_ticket_index = batch
+            .start_ticket_index
+            .checked_add(kept_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        new_batch.num_tickets = num_tickets;
+        new_batch.amount_paid = moved_amount;
+        new_batch.refund_recipient = batch.refund_recipient;
+        new_batch.refunded = false;
+        new_batch.bump = ctx.bumps.new_batch;
+
+        let from_entry = &mut ctx.accounts.from_entry;
+        from_entry.num_tickets = from_entry
+            .num_tickets
+            .checked_sub(num_tickets)
+            .ok_or(RaffleError::InvalidTicketCount)?;
+        from_entry.granted_tickets = from_entry
+            .granted_tickets
+            .checked_sub(moved_granted)
+            .ok_or(RaffleError::Overflow)?;
+
+        let to_entry = &mut ctx.accounts.to_entry;
+        if !to_entry.is_initialized {
+            to_entry.raffle = raffle.key();
+            to_entry.buyer = ctx.accounts.receiver.key();
+            to_entry.num_tickets = 0;
+            to_entry.granted_tickets = 0;
+            to_entry.registered_tickets = 0;
+            to_entry.num_batches = 0;
+            to_entry.is_initialized = true;
+            to_entry.bump = ctx.bumps.to_entry;
+            raffle.num_entries = raffle
+                .num_entries
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+        }
+        to_entry.num_tickets = to_entry
+            .num_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        to_entry.granted_tickets = to_entry
+            .granted_tickets
+            .checked_add(moved_granted)
+            .ok_or(RaffleError::Overflow)?;
+        to_entry.num_batches = to_entry
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+        if raffle.max_per_wallet > 0 {
+            require!(
+                to_entry.num_tickets - to_entry.granted_tickets <= raffle.max_per_wallet,
+                RaffleError::MaxTicketsExceeded
+            );
+        }
+
+        raffle.num_batches = raffle
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+
+        msg!("{} transferred {} tickets to {} | From batch: {} | New batch: {}", 
+            ctx.accounts.sender.key(), num_tickets, ctx.accounts.receiver.key(), batch.index,
+            new_batch.index);
+
+        Ok(())
+    }
+
+    /// Withdraw `num_tickets` bought tickets from the end of one of the buyer's
+    /// batches before `end_time`, on raffles created with a withdraw penalty.
+    /// The tickets are refunded to the batch's refund recipient at what the
+    /// batch paid, less the penalty, which stays in the pot. The freed range is
+    /// recorded as withdrawn so every other ticket keeps its index and the draw
+    /// skips the gap.
+    pub fn withdraw_tickets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawTickets<'info>>,
+        num_tickets: u32,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(clock.unix_timestamp < raffle.end_time, RaffleError::RaffleClosed);
+        require!(raffle.ticket_mint.is_none(), RaffleError::TicketTokenRaffle);
+        let penalty_bps = raffle
+            .withdraw_penalty_bps
+            .ok_or(RaffleError::WithdrawalsDisabled)?;
+        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
+
+        // Granted tickets were never paid for and can't be withdrawn
+        let batch = &ctx.accounts.batch;
+        require!(batch.amount_paid > 0, RaffleError::InvalidTicketBatch);
+        require!(num_tickets <= batch.num_tickets, RaffleError::InvalidTicketCount);
+        let ticket_value = batch.paid_for(num_tickets)?;
+        // Rounded up so even a single cheap ticket pays some penalty
+        let penalty = ticket_value
+            .checked_mul(penalty_bps as u64)
+            .ok_or(RaffleError::Overflow)?
+            .div_ceil(BPS_DENOMINATOR);
+        let payout = ticket_value.checked_sub(penalty).ok_or(RaffleError::Overflow)?;
+
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        pot.pay(
+            ctx.accounts.buyer_token_account.as_ref(),
+            Some(ctx.accounts.refund_recipient.as_ref()),
+            payout,
+        )?;
+
+        // Shrink the batch from its end and record the freed range
+        let batch = &mut ctx.accounts.batch;
+        let kept_tickets = batch
+            .num_tickets
+            .checked_sub(num_tickets)
+            .ok_or(RaffleError::InvalidTicketCount)?;
+        batch.num_tickets = kept_tickets;
+        batch.amount_paid -= ticket_value;
+        let withdrawn = TicketRange {
+            start: batch
+                .start_ticket_index
+                .checked_add(kept_tickets)
+                .ok_or(RaffleError::Overflow)?,
+            num_tickets,
+        };
+
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.add_withdrawn_range(withdrawn)?;
+        raffle.total_tickets = raffle
+            .total_tickets
+            .checked_sub(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        raffle.total_pot = raffle
+            .total_pot
+            .checked_sub(payout)
+            .ok_or(RaffleError::Overflow)?;
+
+        let entry = &mut ctx.accounts.entry;
+        entry.num_tickets -= num_tickets;
+
+        msg!("{} withdrew {} tickets | Batch: {} | Refund: {} | Penalty: {} | Total: {} | Pot: {}", 
+            ctx.accounts.buyer.key(), num_tickets, batch.index, payout, penalty,
+            raffle.total_tickets, raffle.total_pot);
+
+        Ok(())
+    }
+
+    /// Register a holder's ticket tokens for the draw once sales have closed.
+    /// The tokens are burned and their tickets become a batch owned by the
+    /// holder, so the draw runs over whoever held each token when it was
+    /// registered. Tokens not registered within the raffle's registration window
+    /// miss the draw.
+    pub fn register_tickets(ctx: Context<RegisterTickets>) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        require!(raffle.ticket_mint.is_some(), RaffleError::NotTicketTokenRaffle);
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(
+            clock.unix_timestamp >= raffle.end_time || raffle.sold_out(),
+            RaffleError::RaffleNotEnded
+        );
+
+        let amount = ctx.accounts.holder_ticket_account.amount;
+        let num_tickets = u32::try_from(amount).map_err(|_| RaffleError::Overflow)?;
+        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
+
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.ticket_mint.to_account_info(),
+            from: ctx.accounts.holder_ticket_account.to_account_info(),
+            authority: ctx.accounts.holder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        burn(cpi_ctx, amount)?;
+
+        let batch = &mut ctx.accounts.batch;
+        batch.raffle = raffle.key();
+        batch.buyer = ctx.accounts.holder.key();
+        batch.index = raffle.num_batches;
+        batch.start_ticket_index = raffle.registered_tickets;
+        batch.num_tickets = num_tickets;
+        batch.amount_paid = 0;
+        batch.refund_recipient = ctx.accounts.holder.key();
+        batch.refunded = false;
+        batch.bump = ctx.bumps.batch;
+
+        let entry = &mut ctx.accounts.entry;
+        if !entry.is_initialized {
+            entry.raffle = raffle.key();
+            entry.buyer = ctx.accounts.holder.key();
+            entry.num_tickets = 0;
+            entry.granted_tickets = 0;
+            entry.registered_tickets = 0;
+            entry.num_batches = 0;
+            entry.is_initialized = true;
+            entry.bump = ctx.bumps.entry;
+            raffle.num_entries = raffle
+                .num_entries
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+        }
+        entry.num_batches = entry
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+        entry.registered_tickets = entry
+            .registered_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        raffle.registered_tickets = raffle
+            .registered_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        raffle.num_batches = raffle
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+
+        msg!("{} registered {} tickets | Batch: {} | Registered: {}/{}", 
+            ctx.accounts.holder.key(), num_tickets, batch.index, raffle.registered_tickets,
+            raffle.total_tickets);
+
+        Ok(())
+    }
+
+    /// Phase 1: Commit to Switchboard randomness (anyone can crank after deadline,
+    /// or as soon as the raffle sells out).
+    /// Also starts a redraw of unclaimed places once the claim deadline has passed
+    /// on a raffle created with `UnclaimedPrizeMode::Redraw`.
+    /// Bundle this instruction with Switchboard's commitIx in the same tx
+    pub fn commit_draw(ctx: Context<CommitDraw>) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        // Validations
+        if raffle.status == RaffleStatus::DrawComplete {
+            require!(
+                raffle.unclaimed_mode == UnclaimedPrizeMode::Redraw,
+                RaffleError::RedrawNotEnabled
+            );
+            require!(raffle.claim_expired(clock.unix_timestamp)?, RaffleError::ClaimWindowOpen);
+        } else {
+            require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+            // A sold-out raffle can be drawn right away
+            require!(
+                clock.unix_timestamp >= raffle.end_time || raffle.sold_out(),
+                RaffleError::RaffleNotEnded
+            );
+            // Ticket-token holders get until the registration window closes
+            if raffle.ticket_mint.is_some() {
+                let registration_ends = raffle
+                    .end_time
+                    .checked_add(raffle.registration_window)
+                    .ok_or(RaffleError::Overflow)?;
+                require!(
+                    raffle.registered_tickets == raffle.total_tickets
+                        || clock.unix_timestamp >= registration_ends,
+                    RaffleError::RegistrationOpen
+                );
+            }
+            require!(raffle.draw_tickets() > 0, RaffleError::NoTickets);
+            require!(
+                raffle.draw_tickets() as usize >= raffle.prizes.len(),
+                RaffleError::NotEnoughTickets
+            );
+            require!(raffle.total_pot >= raffle.min_pot, RaffleError::ThresholdNotMet);
+            require!(
+                raffle.prize_mode == PrizeMode::Pot || !raffle.prize_assets.is_empty(),
+                RaffleError::NoPrizeAssets
+            );
+        }
+
+        let seed_slot = verify_committed_randomness(&ctx.accounts.randomness_account, &clock)?;
+
+        // Store the randomness account and commit slot
+        raffle.randomness_account = Some(ctx.accounts.randomness_account.key());
+        raffle.commit_slot = Some(seed_slot);
+        raffle.status = RaffleStatus::DrawCommitted;
+
+        msg!("Draw committed! Randomness account: {} | Seed slot: {}", 
+            ctx.accounts.randomness_account.key(), seed_slot);
+
+        Ok(())
+    }
+
+    /// Recover a stuck draw by committing fresh randomness (anyone, once the
+    /// draw has sat in `DrawCommitted` for longer than the draw timeout and its
+    /// randomness can't be revealed)
+    /// Bundle this instruction with Switchboard's commitIx in the same tx
+    pub fn recommit_draw(ctx: Context<RecommitDraw>) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        // Validations
+        require!(raffle.status == RaffleStatus::DrawCommitted, RaffleError::DrawNotCommitted);
+        require!(raffle.draw_timed_out(clock.slot)?, RaffleError::DrawNotTimedOut);
+        verify_randomness_unrevealed(raffle, &ctx.accounts.previous_randomness_account, &clock)?;
+
+        let seed_slot = verify_committed_randomness(&ctx.accounts.randomness_account, &clock)?;
+
+        let previous = raffle.randomness_account;
+        raffle.randomness_account = Some(ctx.accounts.randomness_account.key());
+        raffle.commit_slot = Some(seed_slot);
+
+        msg!("Draw re-committed! Previous randomness: {:?} | New randomness: {} | Seed slot: {}", 
+            previous, ctx.accounts.randomness_account.key(), seed_slot);
+
+        Ok(())
     }
 
-    /// Phase 2: Settle draw using revealed Switchboard randomness
+    /// Phase 2: Settle draw using revealed Switchboard randomness (anyone can crank).
+    /// The keeper is paid `keeper_reward_bps` of the pot out of the platform fee.
     /// Bundle this instruction with Switchboard's revealIx in the same tx
-    pub fn settle_draw(ctx: Context<SettleDraw>) -> Result<()> {
+    pub fn settle_draw<'info>(ctx: Context<'_, '_, 'info, 'info, SettleDraw<'info>>) -> Result<()> {
         let raffle = &mut ctx.accounts.raffle;
         let clock = Clock::get()?;
 
@@ -213,119 +1693,302 @@ pub mod rafflebot {
             .get_value(clock.slot)
             .map_err(|_| RaffleError::RandomnessNotResolved)?;
 
-        // Use randomness to pick winning ticket index
-        let random_value = u64::from_le_bytes(revealed_random_value[0..8].try_into().unwrap());
-        let winning_ticket = (random_value % raffle.total_tickets as u64) as u32;
+        // Use randomness to pick one distinct winning ticket index per unclaimed place.
+        // On a redraw, tickets of claimed places are never reused, and the previous
+        // no-show tickets are skipped too whenever enough tickets remain.
+        let is_redraw = raffle.randomness.is_some();
+        let unclaimed = raffle.prizes.iter().filter(|prize| prize.winner.is_none()).count();
+        // Draw indices skip withdrawn ticket ranges; prizes store ticket indices.
+        let mut exclude: Vec<u32> = raffle
+            .prizes
+            .iter()
+            .filter(|prize| prize.winner.is_some())
+            .filter_map(|prize| prize.winning_ticket)
+            .map(|ticket| raffle.draw_index(ticket))
+            .collect();
+        let no_shows: Vec<u32> = raffle
+            .prizes
+            .iter()
+            .filter(|prize| prize.winner.is_none())
+            .filter_map(|prize| prize.winning_ticket)
+            .map(|ticket| raffle.draw_index(ticket))
+            .collect();
+        if (raffle.draw_tickets() as usize) >= raffle.prizes.len() + no_shows.len() {
+            exclude.extend(no_shows);
+        }
+        let winning_tickets: Vec<u32> = derive_winning_tickets(
+            &revealed_random_value,
+            raffle.draw_tickets(),
+            unclaimed,
+            &exclude,
+        )
+        .into_iter()
+        .map(|draw_index| raffle.ticket_index(draw_index))
+        .collect();
+        for (prize, ticket) in raffle
+            .prizes
+            .iter_mut()
+            .filter(|prize| prize.winner.is_none())
+            .zip(winning_tickets.iter())
+        {
+            prize.winning_ticket = Some(*ticket);
+        }
+
+        // Pay the keeper out of the platform fee (first draw only)
+        let keeper_reward = if is_redraw { 0 } else { raffle.keeper_reward_amount()? };
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        pot.pay(
+            ctx.accounts.keeper_token_account.as_ref(),
+            Some(ctx.accounts.keeper.as_ref()),
+            keeper_reward,
+        )?;
 
-        raffle.winning_ticket = Some(winning_ticket);
         raffle.randomness = Some(revealed_random_value);
+        if is_redraw {
+            raffle.redraw_count = raffle.redraw_count.checked_add(1).ok_or(RaffleError::Overflow)?;
+        } else {
+            raffle.keeper_reward = keeper_reward;
+        }
+        raffle.claim_deadline = Some(
+            clock
+                .unix_timestamp
+                .checked_add(raffle.claim_window)
+                .ok_or(RaffleError::Overflow)?,
+        );
         raffle.status = RaffleStatus::DrawComplete;
 
-        msg!("Winner drawn! Winning ticket index: {} | Randomness: {:?}", 
-            winning_ticket, &revealed_random_value[0..8]);
+        msg!("Winners drawn! Winning ticket indices: {:?} | Randomness: {:?} | Keeper: {} | Reward: {}", 
+            winning_tickets, &revealed_random_value[0..8], ctx.accounts.keeper.key(), keeper_reward);
 
         Ok(())
     }
 
-    /// Claim prize (winner calls this with their entry)
-    pub fn claim_prize(ctx: Context<ClaimPrize>) -> Result<()> {
+    /// Claim the prize for one place (winner calls this with the ticket batch
+    /// holding the winning ticket). The platform fee is paid out alongside the first claim.
+    pub fn claim_prize<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimPrize<'info>>,
+        place: u8,
+    ) -> Result<()> {
         let raffle = &ctx.accounts.raffle;
-        let entry = &ctx.accounts.winner_entry;
+        let batch = &ctx.accounts.winning_batch;
 
         // Validations
         require!(raffle.status == RaffleStatus::DrawComplete, RaffleError::DrawNotComplete);
-        
-        let winning_ticket = raffle.winning_ticket.ok_or(RaffleError::NoWinnerDrawn)?;
-        let entry_end_exclusive = entry
+        require!(
+            !raffle.claim_expired(Clock::get()?.unix_timestamp)?,
+            RaffleError::ClaimDeadlinePassed
+        );
+
+        let prize = raffle
+            .prizes
+            .get(place as usize)
+            .ok_or(RaffleError::InvalidPlace)?;
+        require!(prize.winner.is_none(), RaffleError::PrizeAlreadyClaimed);
+        let winning_ticket = prize.winning_ticket.ok_or(RaffleError::NoWinnerDrawn)?;
+        let batch_end_exclusive = batch
             .start_ticket_index
-            .checked_add(entry.num_tickets)
+            .checked_add(batch.num_tickets)
             .ok_or(RaffleError::Overflow)?;
         
-        // Verify this entry contains the winning ticket
+        // Verify this batch contains the winning ticket
         require!(
-            winning_ticket >= entry.start_ticket_index &&
-            winning_ticket < entry_end_exclusive,
+            winning_ticket >= batch.start_ticket_index &&
+            winning_ticket < batch_end_exclusive,
             RaffleError::NotWinner
         );
-        require!(entry.buyer == ctx.accounts.winner.key(), RaffleError::NotWinner);
+        require!(batch.buyer == ctx.accounts.winner.key(), RaffleError::NotWinner);
 
-        // Calculate splits
-        let platform_fee = raffle.total_pot
-            .checked_mul(PLATFORM_FEE_BPS)
-            .ok_or(RaffleError::Overflow)?
-            .checked_div(BPS_DENOMINATOR)
-            .ok_or(RaffleError::Overflow)?;
+        // Calculate splits (the keeper's reward was already taken out of the fee)
+        let platform_fee = raffle.platform_fee()?;
         
-        let prize_amount = raffle.total_pot
+        let prize_pool = raffle.total_pot
             .checked_sub(platform_fee)
             .ok_or(RaffleError::Overflow)?;
+        // Prize-asset raffles pay the place's share of the pot to the creator as
+        // ticket revenue; the winner receives the assets via `release_prize_asset`
+        let share = raffle.prize_share(prize_pool, place as usize)?;
+        let (prize_amount, revenue_amount) = match raffle.prize_mode {
+            PrizeMode::Pot => (share, 0),
+            PrizeMode::Assets => (0, share),
+        };
+        let fee_amount = if raffle.fee_collected {
+            0
+        } else {
+            platform_fee
+                .checked_sub(raffle.keeper_reward)
+                .ok_or(RaffleError::Overflow)?
+        };
+
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
 
         // Transfer prize to winner
-        let raffle_key = raffle.key();
-        let seeds = &[
-            b"escrow",
-            raffle_key.as_ref(),
-            &[raffle.escrow_bump],
-        ];
-        let signer = &[&seeds[..]];
-        let decimals = ctx.accounts.token_mint.decimals;
+        pot.pay(
+            ctx.accounts.winner_token_account.as_ref(),
+            Some(ctx.accounts.winner.as_ref()),
+            prize_amount,
+        )?;
 
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.escrow.to_account_info(),
-            to: ctx.accounts.winner_token_account.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
+        // Transfer ticket revenue to the creator
+        pot.pay(
+            ctx.accounts.creator_token_account.as_ref(),
+            ctx.accounts.creator.as_deref(),
+            revenue_amount,
+        )?;
+
+        // Transfer platform fee (once, with the first claim)
+        pot.pay(
+            ctx.accounts.platform_token_account.as_ref(),
+            ctx.accounts.treasury.as_deref(),
+            fee_amount,
+        )?;
+
+        // Update raffle status
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.prizes[place as usize].winner = Some(ctx.accounts.winner.key());
+        raffle.fee_collected = true;
+        if raffle.prizes.iter().all(|prize| prize.winner.is_some()) {
+            raffle.status = RaffleStatus::Claimed;
+        }
+
+        msg!("Prize claimed! Place: {} | Winner: {} | Prize: {} | Revenue: {} | Platform fee: {}", 
+            place + 1, ctx.accounts.winner.key(), prize_amount, revenue_amount, fee_amount);
+
+        Ok(())
+    }
+
+    /// Sweep prizes nobody claimed before the claim deadline to the platform
+    /// treasury or the raffle creator, per the raffle's `unclaimed_mode` (anyone can call)
+    pub fn sweep_unclaimed_prizes<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepUnclaimedPrizes<'info>>,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        // Validations
+        require!(raffle.status == RaffleStatus::DrawComplete, RaffleError::DrawNotComplete);
+        require!(raffle.claim_expired(clock.unix_timestamp)?, RaffleError::ClaimWindowOpen);
+        let recipient = match raffle.unclaimed_mode {
+            UnclaimedPrizeMode::SweepToPlatform => ctx.accounts.config.treasury,
+            UnclaimedPrizeMode::SweepToCreator => raffle.authority,
+            UnclaimedPrizeMode::Redraw => return err!(RaffleError::SweepNotEnabled),
         };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        transfer_checked(cpi_ctx, prize_amount, decimals)?;
+        require!(ctx.accounts.recipient.key() == recipient, RaffleError::InvalidSweepRecipient);
 
-        // Transfer platform fee
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.escrow.to_account_info(),
-            to: ctx.accounts.platform_token_account.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
+        // Sum the unclaimed places
+        let platform_fee = raffle.platform_fee()?;
+        let prize_pool = raffle.total_pot
+            .checked_sub(platform_fee)
+            .ok_or(RaffleError::Overflow)?;
+        let mut unclaimed_amount: u64 = 0;
+        for (place, prize) in raffle.prizes.iter().enumerate() {
+            if prize.winner.is_none() {
+                unclaimed_amount = unclaimed_amount
+                    .checked_add(raffle.prize_share(prize_pool, place)?)
+                    .ok_or(RaffleError::Overflow)?;
+            }
+        }
+        // Ticket revenue of a prize-asset raffle always belongs to the creator;
+        // the unclaimed assets themselves are swept via `release_prize_asset`
+        let (sweep_amount, revenue_amount) = match raffle.prize_mode {
+            PrizeMode::Pot => (unclaimed_amount, 0),
+            PrizeMode::Assets => (0, unclaimed_amount),
+        };
+        let fee_amount = if raffle.fee_collected {
+            0
+        } else {
+            platform_fee
+                .checked_sub(raffle.keeper_reward)
+                .ok_or(RaffleError::Overflow)?
         };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        transfer_checked(cpi_ctx, platform_fee, decimals)?;
 
-        // Update raffle status
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+
+        // Transfer unclaimed prizes to the recipient
+        pot.pay(
+            ctx.accounts.recipient_token_account.as_ref(),
+            Some(ctx.accounts.recipient.as_ref()),
+            sweep_amount,
+        )?;
+
+        // Transfer ticket revenue to the creator
+        pot.pay(
+            ctx.accounts.creator_token_account.as_ref(),
+            ctx.accounts.creator.as_deref(),
+            revenue_amount,
+        )?;
+
+        // Transfer platform fee if no prize was ever claimed
+        pot.pay(
+            ctx.accounts.platform_token_account.as_ref(),
+            ctx.accounts.treasury.as_deref(),
+            fee_amount,
+        )?;
+
         let raffle = &mut ctx.accounts.raffle;
-        raffle.winner = Some(ctx.accounts.winner.key());
-        raffle.status = RaffleStatus::Claimed;
+        raffle.fee_collected = true;
+        raffle.status = RaffleStatus::Swept;
 
-        msg!("Prize claimed! Winner: {} | Prize: {} | Platform fee: {}", 
-            ctx.accounts.winner.key(), prize_amount, platform_fee);
+        msg!("Unclaimed prizes swept to {} | Amount: {} | Revenue: {} | Platform fee: {}", 
+            recipient, sweep_amount, revenue_amount, fee_amount);
 
         Ok(())
     }
 
-    /// Cancel raffle and enable refunds (authority only, or auto after deadline if threshold not met)
+    /// Cancel raffle and enable refunds (authority only, or auto after deadline if
+    /// threshold not met, or anyone once a committed draw has timed out; pass the
+    /// committed randomness account then)
     pub fn cancel_raffle(ctx: Context<CancelRaffle>) -> Result<()> {
         let raffle = &mut ctx.accounts.raffle;
         let clock = Clock::get()?;
 
-        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(
+            raffle.status == RaffleStatus::Active || raffle.status == RaffleStatus::DrawCommitted,
+            RaffleError::RaffleNotActive
+        );
 
         // Can cancel if:
         // 1. Authority cancels before deadline, OR
-        // 2. Deadline passed and threshold not met
-        let is_authority = ctx.accounts.authority.key() == raffle.authority;
-        let deadline_passed = clock.unix_timestamp >= raffle.end_time;
-        let threshold_not_met = raffle.total_pot < raffle.min_pot;
+        // 2. Deadline passed and threshold not met (unless the pot rolls over), OR
+        // 3. First draw was committed but never settled within the draw timeout
+        //    (a stuck redraw can only be re-committed: prizes may already be paid)
+        let can_cancel = if raffle.status == RaffleStatus::DrawCommitted {
+            let timed_out = raffle.randomness.is_none() && raffle.draw_timed_out(clock.slot)?;
+            if timed_out {
+                let randomness_account = ctx.accounts.randomness_account.as_ref()
+                    .ok_or(RaffleError::InvalidRandomnessAccount)?;
+                verify_randomness_unrevealed(raffle, randomness_account, &clock)?;
+            }
+            timed_out
+        } else {
+            let is_authority = ctx.accounts.authority.key() == raffle.authority;
+            let deadline_passed = clock.unix_timestamp >= raffle.end_time;
+            let threshold_not_met = raffle.total_pot < raffle.min_pot;
+            let rolls_over = raffle.rollover_grace.is_some();
+            is_authority || (deadline_passed && threshold_not_met && !rolls_over)
+        };
 
-        require!(
-            is_authority || (deadline_passed && threshold_not_met),
-            RaffleError::CannotCancel
-        );
+        require!(can_cancel, RaffleError::CannotCancel);
 
         raffle.status = RaffleStatus::Cancelled;
 
@@ -334,290 +1997,1869 @@ pub mod rafflebot {
         Ok(())
     }
 
-    /// Claim refund (for cancelled raffles)
-    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+    /// Refund what a ticket batch actually paid to the batch's refund recipient
+    /// (for cancelled raffles). Anyone can call, once per batch. On a rolled-over
+    /// raffle this opts the batch out of the next round, so its owner or refund
+    /// recipient must sign: during the grace window, or afterwards if the next
+    /// round can't take the batch. Pass the next round as `next_raffle` after the
+    /// grace window.
+    pub fn claim_refund<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimRefund<'info>>,
+    ) -> Result<()> {
         let raffle = &ctx.accounts.raffle;
-        let entry = &ctx.accounts.entry;
+        let batch = &ctx.accounts.batch;
+        let clock = Clock::get()?;
 
-        require!(raffle.status == RaffleStatus::Cancelled, RaffleError::RaffleNotCancelled);
-        require!(entry.buyer == ctx.accounts.buyer.key(), RaffleError::NotEntryOwner);
-        require!(!entry.refunded, RaffleError::AlreadyRefunded);
+        // Rolled-over raffles refund buyers who opt out of the next round,
+        // until the grace window ends and `roll_batch` takes over
+        require!(
+            matches!(raffle.status, RaffleStatus::Cancelled | RaffleStatus::RolledOver),
+            RaffleError::RaffleNotCancelled
+        );
+        require!(!batch.refunded, RaffleError::AlreadyRefunded);
+        if raffle.status == RaffleStatus::RolledOver {
+            let owner = ctx.accounts.owner.as_ref().ok_or(RaffleError::NotEntryOwner)?;
+            require!(
+                owner.key() == batch.buyer || owner.key() == batch.refund_recipient,
+                RaffleError::NotEntryOwner
+            );
+        }
+        let grace_ended = raffle
+            .rollover_deadline
+            .is_some_and(|deadline| clock.unix_timestamp >= deadline);
+        if raffle.status == RaffleStatus::RolledOver && grace_ended {
+            // A batch the next round can't take (closed, full or gated) would
+            // otherwise be stuck in this pot
+            let next_raffle = ctx
+                .accounts
+                .next_raffle
+                .as_ref()
+                .ok_or(RaffleError::InvalidRolloverTarget)?;
+            let admitted = load_raffle(next_raffle).is_some_and(|next_raffle| {
+                check_rollover_admission(&next_raffle, batch, clock.unix_timestamp, None, None, None)
+                    .is_ok()
+            });
+            require!(!admitted, RaffleError::RolloverGraceEnded);
+        }
 
-        // Full refund for cancelled raffles
-        let refund_amount = raffle.ticket_price
-            .checked_mul(entry.num_tickets as u64)
-            .ok_or(RaffleError::Overflow)?;
+        // Full refund of what the pot received for this batch. Ticket-token
+        // raffles refund registered tickets at the pot's average ticket price;
+        // unregistered tokens are refunded by `refund_ticket_tokens`.
+        let refund_amount = match raffle.ticket_mint {
+            None => batch.amount_paid,
+            Some(_) => raffle.ticket_refund(batch.num_tickets)?,
+        };
 
-        let raffle_key = raffle.key();
-        let seeds = &[
-            b"escrow",
-            raffle_key.as_ref(),
-            &[raffle.escrow_bump],
-        ];
-        let signer = &[&seeds[..]];
-        let decimals = ctx.accounts.token_mint.decimals;
+        // Transfer refund to the recipient
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        pot.pay(
+            ctx.accounts.buyer_token_account.as_ref(),
+            Some(ctx.accounts.refund_recipient.as_ref()),
+            refund_amount,
+        )?;
 
-        // Transfer refund to buyer
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.escrow.to_account_info(),
-            to: ctx.accounts.buyer_token_account.to_account_info(),
-            mint: ctx.accounts.token_mint.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
+        // Mark as refunded
+        let batch = &mut ctx.accounts.batch;
+        batch.refunded = true;
+
+        msg!("Refund claimed: {} | Batch: {} | Refund: {} | To: {}", 
+            batch.buyer, batch.index, refund_amount, ctx.accounts.refund_recipient.key());
+
+        Ok(())
+    }
+
+    /// Roll over a series round whose pot missed `min_pot` by `end_time`, on
+    /// raffles created with a rollover grace window (permissionless). The pot
+    /// and its entries are bound for the series' next round: during the grace
+    /// window buyers can opt out with `claim_refund`; afterwards `roll_batch`
+    /// carries each remaining ticket batch into that round.
+    pub fn rollover_raffle(ctx: Context<RolloverRaffle>) -> Result<()> {
+        let raffle = &mut ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        require!(raffle.status == RaffleStatus::Active, RaffleError::RaffleNotActive);
+        require!(clock.unix_timestamp >= raffle.end_time, RaffleError::RaffleNotEnded);
+        require!(raffle.total_pot < raffle.min_pot, RaffleError::PotThresholdMet);
+        require!(raffle.ticket_mint.is_none(), RaffleError::TicketTokenRaffle);
+        let (Some(grace), Some(series)) = (raffle.rollover_grace, raffle.series) else {
+            return err!(RaffleError::RolloverDisabled);
         };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
+
+        let next_round = raffle.round.checked_add(1).ok_or(RaffleError::Overflow)?;
+        let (next_raffle, _) = Pubkey::find_program_address(
+            &[b"raffle", series.as_ref(), &next_round.to_le_bytes()],
+            ctx.program_id,
         );
-        transfer_checked(cpi_ctx, refund_amount, decimals)?;
+        let rollover_deadline = clock
+            .unix_timestamp
+            .checked_add(grace)
+            .ok_or(RaffleError::Overflow)?;
 
-        // Mark as refunded
-        let entry = &mut ctx.accounts.entry;
-        entry.refunded = true;
+        raffle.status = RaffleStatus::RolledOver;
+        raffle.rollover_to = Some(next_raffle);
+        raffle.rollover_deadline = Some(rollover_deadline);
 
-        msg!("Refund claimed: {} | Refund: {}", ctx.accounts.buyer.key(), refund_amount);
+        msg!("Raffle rolled over: {} #{} | Pot: {} | Next: {} | Opt-out until: {}", 
+            raffle.name, raffle.round, raffle.total_pot, next_raffle, rollover_deadline);
 
         Ok(())
     }
-}
 
-// ============================================================================
-// ACCOUNTS
-// ============================================================================
+    /// Carry a ticket batch of a rolled-over raffle into the next round once
+    /// the grace window has passed (permissionless; the caller pays for the new
+    /// accounts). What the pot received for the batch moves to the next round's
+    /// pot, and its tickets become a new batch there with the same refund recipient.
+    /// The next round's allowlist, holder gate and ticket caps apply; a batch it
+    /// can't take is refunded by `claim_refund` instead.
+    pub fn roll_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RollBatch<'info>>,
+        allowlist: Option<AllowlistProof>,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+        let batch = &ctx.accounts.batch;
+        let next_raffle = &ctx.accounts.next_raffle;
+        let clock = Clock::get()?;
 
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct CreateRaffle<'info> {
-    #[account(
-        init,
+        require!(raffle.status == RaffleStatus::RolledOver, RaffleError::RolloverDisabled);
+        require!(
+            raffle.rollover_deadline.is_some_and(|deadline| clock.unix_timestamp >= deadline),
+            RaffleError::RolloverGraceActive
+        );
+        require!(!batch.refunded, RaffleError::AlreadyRefunded);
+        check_rollover_admission(
+            next_raffle,
+            batch,
+            clock.unix_timestamp,
+            allowlist,
+            ctx.accounts.holding_token_account.as_ref(),
+            ctx.accounts.holding_metadata.as_deref(),
+        )?;
+
+        let num_tickets = batch.num_tickets;
+        // Batches that paid nothing were granted
+        let granted_tickets = if batch.amount_paid == 0 { num_tickets } else { 0 };
+        let total_tickets = next_raffle
+            .total_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+
+        // Move the batch's payment into the next pot, crediting what arrived
+        // net of any Token-2022 transfer fee
+        let next_escrow = ctx.accounts.next_escrow.as_ref();
+        let balance_before = next_escrow
+            .map(|escrow| token_balance(&escrow.to_account_info()))
+            .transpose()?;
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        pot.pay(
+            next_escrow,
+            ctx.accounts.next_vault.as_ref().map(|vault| vault.as_ref()),
+            batch.amount_paid,
+        )?;
+        let received = match (next_escrow, balance_before) {
+            (Some(escrow), Some(balance_before)) => token_balance(&escrow.to_account_info())?
+                .checked_sub(balance_before)
+                .ok_or(RaffleError::Overflow)?,
+            _ => batch.amount_paid,
+        };
+
+        let next_raffle = &mut ctx.accounts.next_raffle;
+        let next_batch = &mut ctx.accounts.next_batch;
+        next_batch.raffle = next_raffle.key();
+        next_batch.buyer = batch.buyer;
+        next_batch.index = next_raffle.num_batches;
+        next_batch.start_ticket_index = next_raffle.next_ticket_index()?;
+        next_batch.num_tickets = num_tickets;
+        next_batch.amount_paid = received;
+        next_batch.refund_recipient = batch.refund_recipient;
+        next_batch.refunded = false;
+        next_batch.bump = ctx.bumps.next_batch;
+
+        let next_entry = &mut ctx.accounts.next_entry;
+        if !next_entry.is_initialized {
+            next_entry.raffle = next_raffle.key();
+            next_entry.buyer = batch.buyer;
+            next_entry.num_tickets = 0;
+            next_entry.granted_tickets = 0;
+            next_entry.registered_tickets = 0;
+            next_entry.num_batches = 0;
+            next_entry.is_initialized = true;
+            next_entry.bump = ctx.bumps.next_entry;
+            next_raffle.num_entries = next_raffle
+                .num_entries
+                .checked_add(1)
+                .ok_or(RaffleError::Overflow)?;
+        }
+        next_entry.num_tickets = next_entry
+            .num_tickets
+            .checked_add(num_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        next_entry.granted_tickets = next_entry
+            .granted_tickets
+            .checked_add(granted_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        next_entry.num_batches = next_entry
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+
+        next_raffle.num_batches = next_raffle
+            .num_batches
+            .checked_add(1)
+            .ok_or(RaffleError::Overflow)?;
+        next_raffle.total_tickets = total_tickets;
+        next_raffle.granted_tickets = next_raffle
+            .granted_tickets
+            .checked_add(granted_tickets)
+            .ok_or(RaffleError::Overflow)?;
+        next_raffle.total_pot = next_raffle
+            .total_pot
+            .checked_add(received)
+            .ok_or(RaffleError::Overflow)?;
+
+        // The old batch is settled; it is closed by `close_entry`
+        let batch = &mut ctx.accounts.batch;
+        batch.refunded = true;
+
+        msg!("Batch rolled over: {} | Tickets: {} | Carried: {} | Next: {} | Batch: {}", 
+            batch.buyer, num_tickets, received, next_raffle.key(), next_batch.index);
+
+        Ok(())
+    }
+
+    /// Refund ticket tokens still held once a ticket-token raffle is cancelled.
+    /// The tokens are burned and refunded at the pot's average ticket price,
+    /// whoever holds them now.
+    pub fn refund_ticket_tokens<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundTicketTokens<'info>>,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+
+        require!(raffle.status == RaffleStatus::Cancelled, RaffleError::RaffleNotCancelled);
+        require!(raffle.ticket_mint.is_some(), RaffleError::NotTicketTokenRaffle);
+
+        let amount = ctx.accounts.holder_ticket_account.amount;
+        let num_tickets = u32::try_from(amount).map_err(|_| RaffleError::Overflow)?;
+        require!(num_tickets > 0, RaffleError::InvalidTicketCount);
+        let refund_amount = raffle.ticket_refund(num_tickets)?;
+
+        let token_program = ctx
+            .accounts
+            .token_program
+            .as_ref()
+            .ok_or(RaffleError::InvalidTicketAccounts)?;
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.ticket_mint.to_account_info(),
+            from: ctx.accounts.holder_ticket_account.to_account_info(),
+            authority: ctx.accounts.holder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+        burn(cpi_ctx, amount)?;
+
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        pot.pay(
+            ctx.accounts.holder_token_account.as_ref(),
+            Some(ctx.accounts.holder.as_ref()),
+            refund_amount,
+        )?;
+
+        msg!("Ticket tokens refunded: {} | Tickets: {} | Refund: {}", 
+            ctx.accounts.holder.key(), num_tickets, refund_amount);
+
+        Ok(())
+    }
+
+    /// Release one deposited prize asset once the raffle is settled (anyone can
+    /// call): to the winner once claimed, to the sweep recipient once swept, or
+    /// back to the creator if cancelled. The emptied vault's rent goes to the creator.
+    pub fn release_prize_asset<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleasePrizeAsset<'info>>,
+        index: u8,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+
+        let asset = raffle
+            .prize_assets
+            .get(index as usize)
+            .ok_or(RaffleError::InvalidPrizeAsset)?;
+        require!(asset.mint == ctx.accounts.prize_mint.key(), RaffleError::InvalidPrizeAsset);
+        require!(!asset.released, RaffleError::PrizeAssetReleased);
+
+        let recipient = match raffle.status {
+            RaffleStatus::Claimed => raffle.prizes[0].winner.ok_or(RaffleError::NoWinnerDrawn)?,
+            RaffleStatus::Cancelled => raffle.authority,
+            RaffleStatus::Swept => match raffle.unclaimed_mode {
+                UnclaimedPrizeMode::SweepToPlatform => ctx.accounts.config.treasury,
+                UnclaimedPrizeMode::SweepToCreator => raffle.authority,
+                UnclaimedPrizeMode::Redraw => return err!(RaffleError::SweepNotEnabled),
+            },
+            _ => return err!(RaffleError::RaffleNotFinished),
+        };
+        require!(
+            ctx.accounts.recipient_token_account.owner == recipient,
+            RaffleError::InvalidPrizeRecipient
+        );
+
+        let raffle_key = raffle.key();
+        let mint_key = asset.mint;
+        let seeds = &[
+            b"prize",
+            raffle_key.as_ref(),
+            mint_key.as_ref(),
+            &[asset.vault_bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Transfer the whole vault balance to the recipient
+        let amount = ctx.accounts.prize_vault.amount;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.prize_vault.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            mint: ctx.accounts.prize_mint.to_account_info(),
+            authority: ctx.accounts.prize_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_checked_with_hook(cpi_ctx, amount, ctx.accounts.prize_mint.decimals)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.prize_vault.to_account_info(),
+            destination: ctx.accounts.authority.to_account_info(),
+            authority: ctx.accounts.prize_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        close_account(cpi_ctx)?;
+
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.prize_assets[index as usize].released = true;
+
+        msg!("Prize asset released: {} | Mint: {} | Amount: {} | To: {}", 
+            raffle.name, mint_key, amount, recipient);
+
+        Ok(())
+    }
+
+    /// Close a buyer's entry and all of their ticket batches once the raffle is
+    /// finished (claimed, swept, or cancelled with every batch refunded). Anyone
+    /// can call; rent always goes back to the buyer. Pass every `TicketBatch`
+    /// owned by the buyer as remaining accounts.
+    pub fn close_entry<'info>(ctx: Context<'_, '_, 'info, 'info, CloseEntry<'info>>) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+        let entry = &ctx.accounts.entry;
+
+        // Cancelled and rolled-over raffles owe each batch a refund first;
+        // granted batches of a cancelled raffle paid nothing and owe none, but
+        // a rolled-over raffle's granted batches still have to be carried over
+        let refunds_due = matches!(
+            raffle.status,
+            RaffleStatus::Cancelled | RaffleStatus::RolledOver
+        );
+        require!(
+            refunds_due || matches!(raffle.status, RaffleStatus::Claimed | RaffleStatus::Swept),
+            RaffleError::RaffleNotFinished
+        );
+        require!(
+            ctx.remaining_accounts.len() == entry.num_batches as usize,
+            RaffleError::MissingTicketBatches
+        );
+
+        // A batch closed earlier in this loop is owned by the system program,
+        // so passing the same batch twice fails deserialization
+        for batch_info in ctx.remaining_accounts.iter() {
+            let batch = Account::<TicketBatch>::try_from(batch_info)?;
+            require!(batch.raffle == raffle.key(), RaffleError::InvalidTicketBatch);
+            require!(batch.buyer == entry.buyer, RaffleError::InvalidTicketBatch);
+            if refunds_due {
+                let refund_owed = raffle.status == RaffleStatus::RolledOver
+                    || raffle.ticket_mint.is_some()
+                    || batch.amount_paid > 0;
+                require!(batch.refunded || !refund_owed, RaffleError::RaffleNotFinished);
+            }
+            batch.close(ctx.accounts.buyer.to_account_info())?;
+        }
+
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.num_entries = raffle
+            .num_entries
+            .checked_sub(1)
+            .ok_or(RaffleError::Overflow)?;
+
+        msg!("Entry closed: {} | Batches closed: {}", 
+            ctx.accounts.buyer.key(), ctx.remaining_accounts.len());
+
+        Ok(())
+    }
+
+    /// Close a finished raffle and its escrow once every entry has been closed,
+    /// returning rent to the authority. Any stray tokens left in escrow go to the
+    /// treasury; a rolled-over raffle's remainder goes to the next round instead
+    /// while that round is open. Fees withheld in the escrow by a Token-2022
+    /// transfer-fee mint must be harvested to the mint first (permissionless),
+    /// or the escrow cannot close.
+    pub fn close_raffle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseRaffle<'info>>,
+    ) -> Result<()> {
+        let raffle = &ctx.accounts.raffle;
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(
+                raffle.status,
+                RaffleStatus::Claimed
+                    | RaffleStatus::Swept
+                    | RaffleStatus::Cancelled
+                    | RaffleStatus::RolledOver
+            ),
+            RaffleError::RaffleNotFinished
+        );
+        require!(raffle.num_entries == 0, RaffleError::EntriesStillOpen);
+        require!(
+            raffle.prize_assets.iter().all(|asset| asset.released),
+            RaffleError::PrizeAssetsOutstanding
+        );
+        // Ticket tokens of a cancelled raffle are refunded from the escrow
+        // until `refund_ticket_tokens` has burned every last one
+        if raffle.status == RaffleStatus::Cancelled && raffle.ticket_mint.is_some() {
+            let ticket_mint = ctx
+                .accounts
+                .ticket_mint
+                .as_ref()
+                .ok_or(RaffleError::InvalidTicketAccounts)?;
+            require!(ticket_mint.supply == 0, RaffleError::TicketRefundsOutstanding);
+        }
+
+        // Sweep any leftover balance so the escrow can be closed
+        let pot = Pot::resolve(
+            raffle,
+            &ctx.accounts.escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            ctx.remaining_accounts,
+        )?;
+        let leftover = pot.balance()?;
+
+        // What a rolled-over pot keeps once every batch has been carried over
+        // or refunded is the withdraw penalties; it goes to the next round
+        // while that round still takes tickets
+        let mut next_round = None;
+        if raffle.status == RaffleStatus::RolledOver {
+            let next_info = ctx
+                .accounts
+                .next_raffle
+                .as_ref()
+                .ok_or(RaffleError::InvalidRolloverTarget)?;
+            next_round = load_raffle(next_info)
+                .filter(|next| {
+                    next.status == RaffleStatus::Active && clock.unix_timestamp < next.end_time
+                })
+                .map(|next| (next_info, next));
+        }
+        match next_round.as_mut() {
+            Some((next_info, next_raffle)) => {
+                let next_escrow = ctx.accounts.next_escrow.as_ref();
+                let next_vault = ctx.accounts.next_vault.as_ref();
+                require!(
+                    next_escrow.is_none_or(|escrow| escrow.key() == next_raffle.escrow)
+                        && next_vault.is_none_or(|vault| vault.key() == next_raffle.escrow),
+                    RaffleError::InvalidPotAccounts
+                );
+                let balance_before = next_escrow
+                    .map(|escrow| token_balance(&escrow.to_account_info()))
+                    .transpose()?;
+                pot.pay(next_escrow, next_vault.map(|vault| vault.as_ref()), leftover)?;
+                let received = match (next_escrow, balance_before) {
+                    (Some(escrow), Some(balance_before)) => token_balance(&escrow.to_account_info())?
+                        .checked_sub(balance_before)
+                        .ok_or(RaffleError::Overflow)?,
+                    _ => leftover,
+                };
+                next_raffle.total_pot = next_raffle
+                    .total_pot
+                    .checked_add(received)
+                    .ok_or(RaffleError::Overflow)?;
+                next_raffle.try_serialize(&mut &mut next_info.try_borrow_mut_data()?[..])?;
+            }
+            None => pot.pay(
+                ctx.accounts.platform_token_account.as_ref(),
+                ctx.accounts.treasury.as_deref(),
+                leftover,
+            )?,
+        }
+
+        // The SOL vault is closed to the authority by its account constraint
+        if let Pot::Spl { escrow, token_program, raffle_key, escrow_bump, .. } = pot {
+            let seeds = &[
+                b"escrow",
+                raffle_key.as_ref(),
+                &[escrow_bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = CloseAccount {
+                account: escrow.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                cpi_accounts,
+                signer,
+            );
+            close_account(cpi_ctx)?;
+        }
+
+        msg!("Raffle closed: {} | Leftover swept: {}", raffle.name, leftover);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ACCOUNTS
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()),
+    )]
+    pub program: Program<'info, crate::program::Rafflebot>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ RaffleError::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        has_one = admin @ RaffleError::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateRaffle<'info> {
+    #[account(
+        init,
         payer = authority,
         space = 8 + Raffle::INIT_SPACE,
         seeds = [b"raffle", authority.key().as_ref(), name.as_bytes()],
         bump
     )]
-    pub raffle: Account<'info, Raffle>,
+    pub raffle: Account<'info, Raffle>,
+
+    /// SPL escrow; omit (with the mint and token program) for a native SOL raffle
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = escrow,
+        token::token_program = token_program,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The SPL token mint (e.g., USDC)
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// SOL vault replacing the escrow for a native SOL raffle
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SolVault::INIT_SPACE,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    /// Pass to opt into ticket tokens: one token per ticket, minted on purchase.
+    /// Needs the token program even for a native SOL raffle.
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"ticket_mint", raffle.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = ticket_mint,
+        mint::token_program = token_program,
+    )]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateSeries<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Series::INIT_SPACE,
+        seeds = [b"series", authority.key().as_ref(), name.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    /// The SPL token mint rounds are sold in; omit for native SOL rounds
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenRound<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"series", series.authority.as_ref(), series.name.as_bytes()],
+        bump = series.bump,
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Raffle::INIT_SPACE,
+        seeds = [b"raffle", series.key().as_ref(), &(series.round + 1).to_le_bytes()],
+        bump
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    /// CHECK: The series' current round, if any; may already be closed
+    #[account(
+        constraint = series.current_raffle.is_none_or(|key| key == previous_raffle.key())
+            @ RaffleError::InvalidPreviousRound,
+    )]
+    pub previous_raffle: UncheckedAccount<'info>,
+
+    /// SPL escrow; omit (with the mint and token program) for a native SOL series
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = escrow,
+        token::token_program = token_program,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_mint.key() == series.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// SOL vault replacing the escrow for a native SOL series
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SolVault::INIT_SPACE,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    /// Pass to opt this round into ticket tokens
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"ticket_mint", raffle.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = ticket_mint,
+        mint::token_program = token_program,
+    )]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositPrize<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"prize", raffle.key().as_ref(), prize_mint.key().as_ref()],
+        bump,
+        token::mint = prize_mint,
+        token::authority = prize_vault,
+        token::token_program = token_program,
+    )]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Mint of the prize asset; screened like a raffle mint, but an NFT may keep
+    /// the freeze authority its Metaplex edition holds
+    pub prize_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == authority.key(),
+        constraint = authority_token_account.mint == prize_mint.key(),
+    )]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRaffle<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GrantTickets<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Entry::INIT_SPACE,
+        seeds = [b"entry", raffle.key().as_ref(), recipient.key().as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, Entry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TicketBatch::INIT_SPACE,
+        seeds = [b"batch", raffle.key().as_ref(), &raffle.num_batches.to_le_bytes()],
+        bump
+    )]
+    pub batch: Account<'info, TicketBatch>,
+
+    /// CHECK: Any wallet; owns the granted tickets
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferTickets<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        mut,
+        seeds = [b"entry", raffle.key().as_ref(), sender.key().as_ref()],
+        bump = from_entry.bump,
+    )]
+    pub from_entry: Account<'info, Entry>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + Entry::INIT_SPACE,
+        seeds = [b"entry", raffle.key().as_ref(), receiver.key().as_ref()],
+        bump
+    )]
+    pub to_entry: Account<'info, Entry>,
+
+    /// Sender's batch the tickets are taken from
+    #[account(
+        mut,
+        seeds = [b"batch", raffle.key().as_ref(), &batch.index.to_le_bytes()],
+        bump = batch.bump,
+        constraint = batch.buyer == sender.key() @ RaffleError::InvalidTicketBatch,
+    )]
+    pub batch: Account<'info, TicketBatch>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + TicketBatch::INIT_SPACE,
+        seeds = [b"batch", raffle.key().as_ref(), &raffle.num_batches.to_le_bytes()],
+        bump
+    )]
+    pub new_batch: Account<'info, TicketBatch>,
+
+    /// CHECK: Any wallet other than the sender; owns the transferred tickets
+    #[account(
+        constraint = receiver.key() != sender.key() @ RaffleError::InvalidTransferRecipient,
+    )]
+    pub receiver: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTickets<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        mut,
+        seeds = [b"entry", raffle.key().as_ref(), buyer.key().as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, Entry>,
+
+    /// Buyer's batch the tickets are taken from
+    #[account(
+        mut,
+        seeds = [b"batch", raffle.key().as_ref(), &batch.index.to_le_bytes()],
+        bump = batch.bump,
+        constraint = batch.buyer == buyer.key() @ RaffleError::InvalidTicketBatch,
+    )]
+    pub batch: Account<'info, TicketBatch>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SOL vault holding the pot of a native raffle
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == batch.refund_recipient,
+        constraint = buyer_token_account.mint == raffle.token_mint,
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_mint.key() == raffle.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Payer or beneficiary recorded on the batch at purchase
+    #[account(
+        mut,
+        address = batch.refund_recipient @ RaffleError::RefundRecipientMismatch,
+    )]
+    pub refund_recipient: UncheckedAccount<'info>,
+
+    pub buyer: Signer<'info>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterTickets<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        init_if_needed,
+        payer = holder,
+        space = 8 + Entry::INIT_SPACE,
+        seeds = [b"entry", raffle.key().as_ref(), holder.key().as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, Entry>,
+
+    #[account(
+        init,
+        payer = holder,
+        space = 8 + TicketBatch::INIT_SPACE,
+        seeds = [b"batch", raffle.key().as_ref(), &raffle.num_batches.to_le_bytes()],
+        bump
+    )]
+    pub batch: Account<'info, TicketBatch>,
+
+    #[account(
+        mut,
+        constraint = Some(ticket_mint.key()) == raffle.ticket_mint @ RaffleError::InvalidTicketAccounts,
+    )]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = holder_ticket_account.owner == holder.key(),
+        constraint = holder_ticket_account.mint == ticket_mint.key(),
+    )]
+    pub holder_ticket_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub holder: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTickets<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Entry::INIT_SPACE,
+        seeds = [b"entry", raffle.key().as_ref(), beneficiary.key().as_ref()],
+        bump
+    )]
+    pub entry: Account<'info, Entry>,
+
+    /// This purchase's ticket range; omit for a ticket-token raffle
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + TicketBatch::INIT_SPACE,
+        seeds = [b"batch", raffle.key().as_ref(), &raffle.num_batches.to_le_bytes()],
+        bump
+    )]
+    pub batch: Option<Account<'info, TicketBatch>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SOL vault holding the pot of a native raffle
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == buyer.key(),
+        constraint = buyer_token_account.mint == raffle.token_mint,
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_mint.key() == raffle.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Proof of holding for a holder-gated raffle: the beneficiary's account of the
+    /// gate mint, or of an NFT in the gate collection
+    #[account(
+        constraint = holding_token_account.owner == beneficiary.key() @ RaffleError::HolderGateFailed,
+    )]
+    pub holding_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of the held NFT, parsed in `verified_collection`;
+    /// only needed for collection gates
+    pub holding_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        constraint = Some(ticket_mint.key()) == raffle.ticket_mint @ RaffleError::InvalidTicketAccounts,
+    )]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Receives the minted ticket tokens of a ticket-token raffle
+    #[account(
+        mut,
+        constraint = beneficiary_ticket_account.owner == beneficiary.key(),
+        constraint = Some(beneficiary_ticket_account.mint) == raffle.ticket_mint @ RaffleError::InvalidTicketAccounts,
+    )]
+    pub beneficiary_ticket_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Pays for the tickets and rent
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Owns the entry and receives any prize; the buyer itself for a
+    /// self-purchase
+    pub beneficiary: UncheckedAccount<'info>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    /// Anyone may crank the draw once the raffle has ended
+    pub keeper: Signer<'info>,
+
+    /// CHECK: Switchboard Randomness account — validated via RandomnessAccountData::parse
+    pub randomness_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecommitDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    /// Anyone may recover a timed-out draw
+    pub keeper: Signer<'info>,
+
+    /// CHECK: Switchboard Randomness account — validated via RandomnessAccountData::parse
+    pub randomness_account: AccountInfo<'info>,
+
+    /// CHECK: Timed-out draw's randomness account — validated against stored key
+    pub previous_randomness_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    /// Anyone may crank the draw; receives the keeper reward
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// CHECK: Switchboard Randomness account — validated against stored key + parsed
+    pub randomness_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SOL vault holding the pot of a native raffle
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    #[account(
+        mut,
+        constraint = keeper_token_account.owner == keeper.key(),
+        constraint = keeper_token_account.mint == raffle.token_mint,
+    )]
+    pub keeper_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_mint.key() == raffle.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPrize<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        seeds = [b"batch", raffle.key().as_ref(), &winning_batch.index.to_le_bytes()],
+        bump = winning_batch.bump,
+    )]
+    pub winning_batch: Account<'info, TicketBatch>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SOL vault holding the pot of a native raffle
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    #[account(
+        mut,
+        constraint = winner_token_account.owner == winner.key(),
+        constraint = winner_token_account.mint == raffle.token_mint,
+    )]
+    pub winner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = platform_token_account.owner == config.treasury,
+        constraint = platform_token_account.mint == raffle.token_mint,
+    )]
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == raffle.authority,
+        constraint = creator_token_account.mint == raffle.token_mint,
+    )]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Creator wallet, receives the ticket revenue of a native prize-asset raffle
+    #[account(
+        mut,
+        address = raffle.authority,
+    )]
+    pub creator: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Treasury wallet, receives the fee of a native raffle
+    #[account(
+        mut,
+        address = config.treasury,
+    )]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = token_mint.key() == raffle.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Winner pubkey checked against the batch buyer and token-account owner
+    #[account(mut)]
+    pub winner: UncheckedAccount<'info>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnclaimedPrizes<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SOL vault holding the pot of a native raffle
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    /// CHECK: Treasury or creator wallet, checked against `raffle.unclaimed_mode`
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == recipient.key(),
+        constraint = recipient_token_account.mint == raffle.token_mint,
+    )]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = platform_token_account.owner == config.treasury,
+        constraint = platform_token_account.mint == raffle.token_mint,
+    )]
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == raffle.authority,
+        constraint = creator_token_account.mint == raffle.token_mint,
+    )]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Creator wallet, receives the ticket revenue of a native prize-asset raffle
+    #[account(
+        mut,
+        address = raffle.authority,
+    )]
+    pub creator: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Treasury wallet, receives the fee of a native raffle
+    #[account(
+        mut,
+        address = config.treasury,
+    )]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        constraint = token_mint.key() == raffle.token_mint,
+    )]
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct RolloverRaffle<'info> {
+    #[account(
+        mut,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+}
+
+#[derive(Accounts)]
+pub struct RollBatch<'info> {
+    #[account(
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
+    )]
+    pub raffle: Account<'info, Raffle>,
+
+    #[account(
+        mut,
+        seeds = [b"batch", raffle.key().as_ref(), &batch.index.to_le_bytes()],
+        bump = batch.bump,
+    )]
+    pub batch: Account<'info, TicketBatch>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    /// The round the rolled-over raffle carries into
+    #[account(
+        mut,
+        constraint = raffle.rollover_to == Some(next_raffle.key()) @ RaffleError::InvalidRolloverTarget,
+        seeds = [b"raffle", next_raffle.seed_key().as_ref(), &next_raffle.seed_suffix()],
+        bump = next_raffle.bump,
+    )]
+    pub next_raffle: Account<'info, Raffle>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Entry::INIT_SPACE,
+        seeds = [b"entry", next_raffle.key().as_ref(), batch.buyer.as_ref()],
+        bump
+    )]
+    pub next_entry: Account<'info, Entry>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TicketBatch::INIT_SPACE,
+        seeds = [b"batch", next_raffle.key().as_ref(), &next_raffle.num_batches.to_le_bytes()],
+        bump
+    )]
+    pub next_batch: Account<'info, TicketBatch>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", next_raffle.key().as_ref()],
+        bump = next_raffle.escrow_bump,
+    )]
+    pub next_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", next_raffle.key().as_ref()],
+        bump = next_raffle.escrow_bump,
+    )]
+    pub next_vault: Option<Account<'info, SolVault>>,
 
     #[account(
-        init,
-        payer = authority,
-        seeds = [b"escrow", raffle.key().as_ref()],
-        bump,
-        token::mint = token_mint,
-        token::authority = escrow,
-        token::token_program = token_program,
+        constraint = token_mint.key() == raffle.token_mint,
     )]
-    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
 
-    /// The SPL token mint (e.g., USDC)
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    /// Proof of holding when the next round is holder-gated: the batch owner's
+    /// account of the gate mint, or of an NFT in the gate collection
+    pub holding_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    /// Platform wallet to receive fees
-    /// CHECK: Just storing the pubkey, validated on claim
-    pub platform_wallet: UncheckedAccount<'info>,
+    /// CHECK: Metaplex metadata of the held NFT, parsed in `verified_collection`;
+    /// only needed for collection gates
+    pub holding_metadata: Option<UncheckedAccount<'info>>,
 
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub payer: Signer<'info>,
 
-    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct BuyTickets<'info> {
+pub struct RefundTicketTokens<'info> {
     #[account(
-        mut,
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
         bump = raffle.bump,
     )]
     pub raffle: Account<'info, Raffle>,
 
     #[account(
-        init_if_needed,
-        payer = buyer,
-        space = 8 + Entry::INIT_SPACE,
-        seeds = [b"entry", raffle.key().as_ref(), buyer.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"escrow", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
     )]
-    pub entry: Account<'info, Entry>,
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// SOL vault holding the pot of a native raffle
     #[account(
         mut,
-        seeds = [b"escrow", raffle.key().as_ref()],
+        seeds = [b"vault", raffle.key().as_ref()],
         bump = raffle.escrow_bump,
     )]
-    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<Account<'info, SolVault>>,
 
     #[account(
         mut,
-        constraint = buyer_token_account.owner == buyer.key(),
-        constraint = buyer_token_account.mint == raffle.token_mint,
+        constraint = holder_token_account.owner == holder.key(),
+        constraint = holder_token_account.mint == raffle.token_mint,
     )]
-    pub buyer_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub holder_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         constraint = token_mint.key() == raffle.token_mint,
     )]
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = Some(ticket_mint.key()) == raffle.ticket_mint @ RaffleError::InvalidTicketAccounts,
+    )]
+    pub ticket_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = holder_ticket_account.owner == holder.key(),
+        constraint = holder_ticket_account.mint == ticket_mint.key(),
+    )]
+    pub holder_ticket_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub buyer: Signer<'info>,
+    pub holder: Signer<'info>,
 
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+    /// Token program of the ticket mint, and of the pot for SPL raffles
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[derive(Accounts)]
-pub struct CommitDraw<'info> {
+pub struct CancelRaffle<'info> {
     #[account(
         mut,
-        has_one = authority,
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
         bump = raffle.bump,
     )]
     pub raffle: Account<'info, Raffle>,
 
+    /// Can be authority OR anyone (if deadline passed + threshold not met, or draw timed out)
     pub authority: Signer<'info>,
 
-    /// CHECK: Switchboard Randomness account — validated via RandomnessAccountData::parse
-    pub randomness_account: AccountInfo<'info>,
+    /// CHECK: Committed randomness account, required to cancel a timed-out draw —
+    /// validated against stored key + parsed
+    pub randomness_account: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct SettleDraw<'info> {
+pub struct ReleasePrizeAsset<'info> {
     #[account(
         mut,
-        has_one = authority,
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
         bump = raffle.bump,
     )]
     pub raffle: Account<'info, Raffle>,
 
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"prize", raffle.key().as_ref(), prize_mint.key().as_ref()],
+        bump,
+    )]
+    pub prize_vault: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: Switchboard Randomness account — validated against stored key + parsed
-    pub randomness_account: AccountInfo<'info>,
+    pub prize_mint: InterfaceAccount<'info, Mint>,
+
+    /// Winner, sweep recipient or creator token account, checked in the handler
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == prize_mint.key(),
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Raffle creator, receives the vault rent
+    #[account(
+        mut,
+        address = raffle.authority,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimPrize<'info> {
+pub struct CloseEntry<'info> {
     #[account(
         mut,
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
         bump = raffle.bump,
     )]
     pub raffle: Account<'info, Raffle>,
 
     #[account(
-        seeds = [b"entry", raffle.key().as_ref(), winner.key().as_ref()],
-        bump = winner_entry.bump,
+        mut,
+        close = buyer,
+        seeds = [b"entry", raffle.key().as_ref(), buyer.key().as_ref()],
+        bump = entry.bump,
+    )]
+    pub entry: Account<'info, Entry>,
+
+    /// CHECK: Buyer pubkey used for entry PDA derivation; receives the rent
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRaffle<'info> {
+    #[account(
+        mut,
+        close = authority,
+        has_one = authority,
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
+        bump = raffle.bump,
     )]
-    pub winner_entry: Account<'info, Entry>,
+    pub raffle: Account<'info, Raffle>,
 
     #[account(
         mut,
         seeds = [b"escrow", raffle.key().as_ref()],
         bump = raffle.escrow_bump,
     )]
-    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = winner_token_account.owner == winner.key(),
-        constraint = winner_token_account.mint == raffle.token_mint,
+        close = authority,
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
     )]
-    pub winner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<Account<'info, SolVault>>,
 
     #[account(
         mut,
-        constraint = platform_token_account.owner == raffle.platform_wallet,
+        constraint = platform_token_account.owner == config.treasury,
         constraint = platform_token_account.mint == raffle.token_mint,
     )]
-    pub platform_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Treasury wallet, receives the fee of a native raffle
+    #[account(
+        mut,
+        address = config.treasury,
+    )]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
 
     #[account(
         constraint = token_mint.key() == raffle.token_mint,
     )]
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
 
-    /// CHECK: Winner pubkey used for entry PDA derivation and token-account owner checks
-    pub winner: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-}
+    /// Ticket token mint of a ticket-token raffle, checked for unrefunded tokens
+    #[account(
+        constraint = Some(ticket_mint.key()) == raffle.ticket_mint @ RaffleError::InvalidTicketAccounts,
+    )]
+    pub ticket_mint: Option<InterfaceAccount<'info, Mint>>,
 
-#[derive(Accounts)]
-pub struct CancelRaffle<'info> {
+    /// CHECK: Round a rolled-over raffle rolled into, read in `load_raffle`;
+    /// may already be closed
     #[account(
         mut,
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
-        bump = raffle.bump,
+        constraint = Some(next_raffle.key()) == raffle.rollover_to @ RaffleError::InvalidRolloverTarget,
     )]
-    pub raffle: Account<'info, Raffle>,
+    pub next_raffle: Option<UncheckedAccount<'info>>,
+
+    /// Next round's escrow, checked against `next_raffle` in the handler
+    #[account(mut)]
+    pub next_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Next round's SOL vault, checked against `next_raffle` in the handler
+    #[account(mut)]
+    pub next_vault: Option<Account<'info, SolVault>>,
 
-    /// Can be authority OR anyone (if deadline passed + threshold not met)
+    #[account(mut)]
     pub authority: Signer<'info>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[derive(Accounts)]
 pub struct ClaimRefund<'info> {
     #[account(
-        seeds = [b"raffle", raffle.authority.as_ref(), raffle.name.as_bytes()],
+        seeds = [b"raffle", raffle.seed_key().as_ref(), &raffle.seed_suffix()],
         bump = raffle.bump,
     )]
     pub raffle: Account<'info, Raffle>,
 
     #[account(
         mut,
-        seeds = [b"entry", raffle.key().as_ref(), buyer.key().as_ref()],
-        bump = entry.bump,
+        seeds = [b"batch", raffle.key().as_ref(), &batch.index.to_le_bytes()],
+        bump = batch.bump,
     )]
-    pub entry: Account<'info, Entry>,
+    pub batch: Account<'info, TicketBatch>,
 
     #[account(
         mut,
         seeds = [b"escrow", raffle.key().as_ref()],
         bump = raffle.escrow_bump,
     )]
-    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// SOL vault holding the pot of a native raffle
     #[account(
         mut,
-        constraint = buyer_token_account.owner == buyer.key(),
+        seeds = [b"vault", raffle.key().as_ref()],
+        bump = raffle.escrow_bump,
+    )]
+    pub vault: Option<Account<'info, SolVault>>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == batch.refund_recipient,
         constraint = buyer_token_account.mint == raffle.token_mint,
     )]
-    pub buyer_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         constraint = token_mint.key() == raffle.token_mint,
     )]
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
 
-    /// CHECK: Buyer pubkey used for entry PDA derivation and token-account owner checks
-    pub buyer: UncheckedAccount<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Payer or beneficiary recorded on the batch at purchase
+    #[account(
+        mut,
+        address = batch.refund_recipient @ RaffleError::RefundRecipientMismatch,
+    )]
+    pub refund_recipient: UncheckedAccount<'info>,
+
+    /// CHECK: Round a rolled-over raffle rolled into, read in `load_raffle`;
+    /// may already be closed
+    #[account(
+        constraint = Some(next_raffle.key()) == raffle.rollover_to @ RaffleError::InvalidRolloverTarget,
+    )]
+    pub next_raffle: Option<UncheckedAccount<'info>>,
+
+    /// Batch owner or refund recipient opting out of a rollover
+    pub owner: Option<Signer<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 // ============================================================================
 // STATE
 // ============================================================================
 
+/// Singleton protocol config, PDA `[b"config"]`
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    /// Platform treasury that receives fees
+    pub treasury: Pubkey,
+    /// Platform fee applied to newly created raffles
+    pub fee_bps: u16,
+    /// Slots after `commit_slot` before a stuck draw can be re-committed or cancelled
+    pub draw_timeout_slots: u64,
+    /// Share of the pot paid to whoever settles a draw, taken out of the platform fee
+    pub keeper_reward_bps: u16,
+    /// Mints raffles may be created with; empty means any mint that passes
+    /// the mint safety screen
+    #[max_len(MAX_ALLOWED_MINTS)]
+    pub allowed_mints: Vec<Pubkey>,
+    /// Vetted mints exempt from the mint safety screen
+    #[max_len(MAX_ALLOWED_MINTS)]
+    pub screen_exempt_mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Recurring raffle series, PDA `[b"series", authority, name]`. Rounds are
+/// raffles at PDA `[b"raffle", series, round]` with `round` as little-endian u32.
+#[account]
+#[derive(InitSpace)]
+pub struct Series {
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub name: String,
+    pub currency: Currency,
+    /// `Pubkey::default()` for native SOL series
+    pub token_mint: Pubkey,
+    pub ticket_price: u64,
+    pub min_pot: u64,
+    pub max_per_wallet: u32,
+    /// Seconds each round runs from when it opens
+    pub duration: i64,
+    /// Template applied to every round
+    pub options: RaffleOptions,
+    /// Rounds opened so far; round numbers start at 1
+    pub round: u32,
+    /// Latest round's raffle
+    pub current_raffle: Option<Pubkey>,
+    pub bump: u8,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Raffle {
     pub authority: Pubkey,
     #[max_len(32)]
     pub name: String,
+    /// Series this raffle is a round of; `None` for standalone raffles
+    pub series: Option<Pubkey>,
+    /// Round number within `series`; 0 for standalone raffles
+    pub round: u32,
+    /// Off-chain description / artwork; editable until the first sale
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: String,
+    pub currency: Currency,
+    /// `Pubkey::default()` for native SOL raffles
     pub token_mint: Pubkey,
+    /// SPL escrow token account, or the SOL vault for native raffles
     pub escrow: Pubkey,
-    pub platform_wallet: Pubkey,
+    /// Platform fee snapshotted from `Config` at creation
+    pub fee_bps: u16,
+    /// Draw timeout snapshotted from `Config` at creation
+    pub draw_timeout_slots: u64,
+    /// Keeper reward snapshotted from `Config` at creation
+    pub keeper_reward_bps: u16,
+    /// Amount paid to the keeper that settled the draw
+    pub keeper_reward: u64,
+    /// Base price of one ticket, before the pricing schedule applies
     pub ticket_price: u64,
+    pub pricing: PricingSchedule,
     pub min_pot: u64,
     pub max_per_wallet: u32,
+    /// Total ticket supply cap; 0 means unlimited
+    pub max_tickets: u32,
+    /// Cap on tickets handed out by `grant_tickets`; 0 means unlimited
+    pub max_granted_tickets: u32,
+    /// Tickets handed out by `grant_tickets`, included in `total_tickets`
+    pub granted_tickets: u32,
+    /// Ticket sales open at this time
+    pub start_time: i64,
+    /// Current deadline, including any soft-close extensions
     pub end_time: i64,
+    pub soft_close: Option<SoftClose>,
+    /// Root of the buyer allowlist built with `merkle::MerkleTree`; `None` is open to all
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Token or collection buyers must hold; `None` is open to all
+    pub holder_gate: Option<HolderGate>,
+    /// Ticket token mint, PDA `[b"ticket_mint", raffle]`; `None` for the `Entry` model
+    pub ticket_mint: Option<Pubkey>,
+    pub ticket_mint_bump: u8,
+    /// Ticket tokens registered for the draw; ticket-token raffles draw from these
+    pub registered_tickets: u32,
+    /// Seconds after sales close that ticket-token holders have to register
+    pub registration_window: i64,
+    /// Penalty kept from `withdraw_tickets` refunds; `None` disables withdrawals
+    pub withdraw_penalty_bps: Option<u16>,
+    /// Ticket ranges freed by withdrawals, sorted by start; the draw skips them
+    #[max_len(MAX_WITHDRAWN_RANGES)]
+    pub withdrawn: Vec<TicketRange>,
+    /// Opt-out window after an unmet pot rolls over; `None` disables rollover
+    pub rollover_grace: Option<i64>,
+    /// Next round an unmet pot rolled over into
+    pub rollover_to: Option<Pubkey>,
+    /// End of the opt-out window; entries are carried over after it
+    pub rollover_deadline: Option<i64>,
+    /// Number of times a late buy extended `end_time`
+    pub num_extensions: u16,
     pub total_tickets: u32,
     pub total_pot: u64,
+    /// Number of `TicketBatch` records created (one per purchase)
+    pub num_batches: u32,
+    /// Number of `Entry` accounts still open; must reach 0 before `close_raffle`
+    pub num_entries: u32,
     pub status: RaffleStatus,
-    pub winner: Option<Pubkey>,
-    pub winning_ticket: Option<u32>,
+    #[max_len(MAX_WINNERS)]
+    pub prizes: Vec<Prize>,
+    pub fee_collected: bool,
+    pub prize_mode: PrizeMode,
+    /// Assets deposited by the creator for a `PrizeMode::Assets` raffle
+    #[max_len(MAX_PRIZE_ASSETS)]
+    pub prize_assets: Vec<PrizeAsset>,
+    /// Seconds winners have to claim after each draw settles
+    pub claim_window: i64,
+    pub claim_deadline: Option<i64>,
+    pub unclaimed_mode: UnclaimedPrizeMode,
+    pub redraw_count: u8,
     pub randomness: Option<[u8; 32]>,
     pub randomness_account: Option<Pubkey>,
     pub commit_slot: Option<u64>,
@@ -626,18 +3868,430 @@ pub struct Raffle {
     pub escrow_bump: u8,
 }
 
+impl Raffle {
+    /// Key in the raffle's PDA seeds: the authority, or the series for a round
+    pub fn seed_key(&self) -> Pubkey {
+        self.series.unwrap_or(self.authority)
+    }
+
+    /// Last PDA seed: the name, or the round number for a series round
+    pub fn seed_suffix(&self) -> Vec<u8> {
+        match self.series {
+            Some(_) => self.round.to_le_bytes().to_vec(),
+            None => self.name.as_bytes().to_vec(),
+        }
+    }
+
+    /// Apply `options` and the config snapshot to a new raffle and zero its
+    /// counters. Identity, pricing, timing and pot fields are left to the caller.
+    fn init_state(&mut self, options: RaffleOptions, config: &Config, now: i64) {
+        self.metadata_uri = options.metadata_uri;
+        self.pricing = options.pricing;
+        self.max_tickets = options.max_tickets;
+        self.max_granted_tickets = options.max_granted_tickets;
+        self.granted_tickets = 0;
+        self.soft_close = options.soft_close;
+        self.allowlist_root = options.allowlist_root;
+        self.holder_gate = options.holder_gate;
+        self.registered_tickets = 0;
+        self.registration_window = options
+            .registration_window
+            .unwrap_or(TICKET_REGISTRATION_WINDOW);
+        self.withdraw_penalty_bps = options.withdraw_penalty_bps;
+        self.withdrawn = Vec::new();
+        self.rollover_grace = options.rollover_grace;
+        self.rollover_to = None;
+        self.rollover_deadline = None;
+        self.num_extensions = 0;
+        self.total_tickets = 0;
+        self.total_pot = 0;
+        self.num_batches = 0;
+        self.num_entries = 0;
+        self.status = RaffleStatus::Active;
+        self.prizes = options
+            .prize_tiers
+            .iter()
+            .map(|bps| Prize { bps: *bps, winning_ticket: None, winner: None })
+            .collect();
+        self.fee_collected = false;
+        self.prize_mode = options.prize_mode;
+        self.prize_assets = Vec::new();
+        self.claim_window = options.claim_window;
+        self.claim_deadline = None;
+        self.unclaimed_mode = options.unclaimed_mode;
+        self.redraw_count = 0;
+        self.randomness = None;
+        self.randomness_account = None;
+        self.commit_slot = None;
+        self.fee_bps = config.fee_bps;
+        self.draw_timeout_slots = config.draw_timeout_slots;
+        self.keeper_reward_bps = config.keeper_reward_bps;
+        self.keeper_reward = 0;
+        self.created_at = now;
+    }
+
+    /// Index the next ticket sold starts at: tickets in play plus withdrawn ranges
+    pub fn next_ticket_index(&self) -> Result<u32> {
+        self.withdrawn
+            .iter()
+            .try_fold(self.total_tickets, |index, range| index.checked_add(range.num_tickets))
+            .ok_or(RaffleError::Overflow.into())
+    }
+
+    /// Ticket index that draw index `0..draw_tickets()` lands on, skipping
+    /// withdrawn ranges
+    pub fn ticket_index(&self, draw_index: u32) -> u32 {
+        let mut index = draw_index;
+        for range in &self.withdrawn {
+            if range.start > index {
+                break;
+            }
+            index += range.num_tickets;
+        }
+        index
+    }
+
+    /// Inverse of `ticket_index` for a ticket still in play
+    pub fn draw_index(&self, ticket_index: u32) -> u32 {
+        let skipped: u32 = self
+            .withdrawn
+            .iter()
+            .filter(|range| range.start < ticket_index)
+            .map(|range| range.num_tickets)
+            .sum();
+        ticket_index - skipped
+    }
+
+    /// Record a withdrawn range, merging it with touching ranges. A range at
+    /// the end of the ticket space is dropped instead, so new sales reuse it.
+    fn add_withdrawn_range(&mut self, range: TicketRange) -> Result<()> {
+        let mut end_index = self.next_ticket_index()?;
+        let range_end = range.start + range.num_tickets;
+        let pos = self.withdrawn.partition_point(|r| r.start < range.start);
+        let touches_prev = pos > 0 && {
+            let prev = &self.withdrawn[pos - 1];
+            prev.start + prev.num_tickets == range.start
+        };
+        let touches_next = self.withdrawn.get(pos).is_some_and(|next| next.start == range_end);
+        match (touches_prev, touches_next) {
+            (true, true) => {
+                let next = self.withdrawn.remove(pos);
+                self.withdrawn[pos - 1].num_tickets += range.num_tickets + next.num_tickets;
+            }
+            (true, false) => self.withdrawn[pos - 1].num_tickets += range.num_tickets,
+            (false, true) => {
+                self.withdrawn[pos].start = range.start;
+                self.withdrawn[pos].num_tickets += range.num_tickets;
+            }
+            (false, false) => {
+                if range_end != end_index {
+                    require!(
+                        self.withdrawn.len() < MAX_WITHDRAWN_RANGES,
+                        RaffleError::TooManyWithdrawals
+                    );
+                }
+                self.withdrawn.insert(pos, range);
+            }
+        }
+        while let Some(last) = self.withdrawn.last() {
+            if last.start + last.num_tickets != end_index {
+                break;
+            }
+            end_index = last.start;
+            self.withdrawn.pop();
+        }
+        Ok(())
+    }
+
+    /// Tickets the draw picks from: every ticket sold, or for a ticket-token
+    /// raffle only those registered after sales closed
+    pub fn draw_tickets(&self) -> u32 {
+        match self.ticket_mint {
+            None => self.total_tickets,
+            Some(_) => self.registered_tickets,
+        }
+    }
+
+    /// Refund for `num_tickets` of a ticket-token raffle, at the pot's average
+    /// ticket price since tokens may have changed hands
+    pub fn ticket_refund(&self, num_tickets: u32) -> Result<u64> {
+        let refund = (self.total_pot as u128)
+            .checked_mul(num_tickets as u128)
+            .ok_or(RaffleError::Overflow)?
+            .checked_div(self.total_tickets as u128)
+            .ok_or(RaffleError::Overflow)?;
+        u64::try_from(refund).map_err(|_| RaffleError::Overflow.into())
+    }
+
+    /// True once every ticket under the supply cap has been sold
+    pub fn sold_out(&self) -> bool {
+        self.max_tickets > 0 && self.total_tickets >= self.max_tickets
+    }
+
+    /// True once the claim deadline of the latest draw has passed
+    pub fn claim_expired(&self, now: i64) -> Result<bool> {
+        let claim_deadline = self.claim_deadline.ok_or(RaffleError::DrawNotComplete)?;
+        Ok(now > claim_deadline)
+    }
+
+    /// Platform fee owed on the pot, including the keeper's cut
+    pub fn platform_fee(&self) -> Result<u64> {
+        self.total_pot
+            .checked_mul(self.fee_bps as u64)
+            .ok_or(RaffleError::Overflow)?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or(RaffleError::Overflow.into())
+    }
+
+    /// Keeper reward on the pot, capped at the platform fee it is paid from
+    pub fn keeper_reward_amount(&self) -> Result<u64> {
+        let reward = self.total_pot
+            .checked_mul(self.keeper_reward_bps as u64)
+            .ok_or(RaffleError::Overflow)?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or(RaffleError::Overflow)?;
+        Ok(reward.min(self.platform_fee()?))
+    }
+
+    /// True once a committed draw has gone unsettled for longer than the draw timeout
+    pub fn draw_timed_out(&self, slot: u64) -> Result<bool> {
+        let commit_slot = self.commit_slot.ok_or(RaffleError::DrawNotCommitted)?;
+        let deadline = commit_slot
+            .checked_add(self.draw_timeout_slots)
+            .ok_or(RaffleError::Overflow)?;
+        Ok(slot > deadline)
+    }
+
+    /// Amount owed to `place` out of `prize_pool`. Place 0 absorbs the rounding
+    /// remainder so the places always sum to exactly `prize_pool`.
+    pub fn prize_share(&self, prize_pool: u64, place: usize) -> Result<u64> {
+        let share = |bps: u16| -> Result<u64> {
+            prize_pool
+                .checked_mul(bps as u64)
+                .ok_or(RaffleError::Overflow)?
+                .checked_div(BPS_DENOMINATOR)
+                .ok_or(RaffleError::Overflow.into())
+        };
+
+        if place > 0 {
+            return share(self.prizes[place].bps);
+        }
+        let mut others: u64 = 0;
+        for prize in self.prizes.iter().skip(1) {
+            others = others.checked_add(share(prize.bps)?).ok_or(RaffleError::Overflow)?;
+        }
+        prize_pool.checked_sub(others).ok_or(RaffleError::Overflow.into())
+    }
+
+    /// Cost of `num_tickets` bought at `now`: the base price plus the markup of the
+    /// steepest time tier in effect, less the discount of the largest bulk tier
+    /// the purchase qualifies for
+    pub fn ticket_cost(&self, num_tickets: u32, now: i64) -> Result<u64> {
+        let seconds_left = self.end_time.saturating_sub(now);
+        let markup_bps = self
+            .pricing
+            .time_tiers
+            .iter()
+            .filter(|tier| seconds_left <= tier.seconds_before_end)
+            .map(|tier| tier.markup_bps as u128)
+            .max()
+            .unwrap_or(0);
+        let discount_bps = self
+            .pricing
+            .bulk_tiers
+            .iter()
+            .filter(|tier| num_tickets >= tier.min_tickets)
+            .map(|tier| tier.discount_bps as u128)
+            .max()
+            .unwrap_or(0);
+
+        let bps = BPS_DENOMINATOR as u128;
+        let cost = (self.ticket_price as u128)
+            .checked_mul(num_tickets as u128)
+            .and_then(|c| c.checked_mul(bps + markup_bps))
+            .and_then(|c| c.checked_mul(bps - discount_bps))
+            .ok_or(RaffleError::Overflow)?
+            / (bps * bps);
+        u64::try_from(cost).map_err(|_| RaffleError::Overflow.into())
+    }
+}
+
+/// Optional raffle settings passed to `create_raffle` and `create_series`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RaffleOptions {
+    /// Share of the prize pool per place in basis points, summing to 10000
+    #[max_len(MAX_WINNERS)]
+    pub prize_tiers: Vec<u16>,
+    /// Seconds winners have to claim after each draw settles
+    pub claim_window: i64,
+    pub unclaimed_mode: UnclaimedPrizeMode,
+    pub prize_mode: PrizeMode,
+    /// Total ticket supply cap; 0 means unlimited
+    pub max_tickets: u32,
+    /// Cap on tickets the authority can grant for free; 0 means unlimited
+    pub max_granted_tickets: u32,
+    /// When ticket sales open; `None` opens them immediately
+    pub start_time: Option<i64>,
+    /// Anti-sniping rule; `None` keeps a hard deadline
+    pub soft_close: Option<SoftClose>,
+    /// Off-chain description / artwork; may be empty
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub metadata_uri: String,
+    /// Bulk discounts and time-based markups; empty tiers keep a flat price
+    pub pricing: PricingSchedule,
+    /// Merkle root of the buyer allowlist; `None` lets anyone buy
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Token or collection buyers must hold; `None` lets anyone buy
+    pub holder_gate: Option<HolderGate>,
+    /// Penalty kept when a buyer withdraws before `end_time`, at least
+    /// `MIN_WITHDRAW_PENALTY_BPS`; `None` disables withdrawals
+    pub withdraw_penalty_bps: Option<u16>,
+    /// Series rounds only: roll an unmet pot into the next round, with this
+    /// many seconds for buyers to opt out; `None` refunds on cancellation
+    pub rollover_grace: Option<i64>,
+    /// Ticket-token raffles only: seconds after sales close for holders to
+    /// register; `None` uses `TICKET_REGISTRATION_WINDOW`
+    pub registration_window: Option<i64>,
+}
+
+/// A contiguous range of ticket indices
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct TicketRange {
+    pub start: u32,
+    pub num_tickets: u32,
+}
+
+/// Buyers must hold at least `min_balance` of the gate mint, or of an NFT
+/// verified in the gate collection
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct HolderGate {
+    pub kind: GateKind,
+    /// Gate mint, or collection mint for `GateKind::Collection`
+    pub key: Pubkey,
+    pub min_balance: u64,
+}
+
+/// A buyer's allowlist leaf and its Merkle proof, from `merkle::MerkleTree`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AllowlistProof {
+    /// Ticket allowance committed in the leaf; 0 falls back to `max_per_wallet`
+    pub allowance: u32,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Ticket pricing on top of the base `ticket_price`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct PricingSchedule {
+    #[max_len(MAX_PRICE_TIERS)]
+    pub bulk_tiers: Vec<BulkTier>,
+    #[max_len(MAX_PRICE_TIERS)]
+    pub time_tiers: Vec<TimeTier>,
+}
+
+/// Purchases of at least `min_tickets` get `discount_bps` off, e.g.
+/// 10 tickets at 2000 bps pay for 8
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct BulkTier {
+    pub min_tickets: u32,
+    pub discount_bps: u16,
+}
+
+/// Purchases within `seconds_before_end` of `end_time` pay `markup_bps` extra
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct TimeTier {
+    pub seconds_before_end: i64,
+    pub markup_bps: u16,
+}
+
+/// Anti-sniping rule: a buy within `window` seconds of `end_time` pushes
+/// `end_time` out by `extension` seconds, never past `max_end_time`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct SoftClose {
+    pub window: i64,
+    pub extension: i64,
+    pub max_end_time: i64,
+}
+
+/// One place in a raffle's prize table
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Prize {
+    /// Share of the prize pool (pot minus platform fee) in basis points
+    pub bps: u16,
+    pub winning_ticket: Option<u32>,
+    /// Set once the prize for this place has been paid out
+    pub winner: Option<Pubkey>,
+}
+
+/// A prize asset held in its vault, PDA `[b"prize", raffle, mint]`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct PrizeAsset {
+    pub mint: Pubkey,
+    /// Amount the vault received
+    pub amount: u64,
+    pub vault_bump: u8,
+    /// Set once the asset has left the vault
+    pub released: bool,
+}
+
+/// Program-owned vault holding the lamports of a native SOL raffle,
+/// PDA `[b"vault", raffle]`
+#[account]
+#[derive(InitSpace)]
+pub struct SolVault {
+    pub bump: u8,
+}
+
+/// Per-wallet totals, used for `max_per_wallet` and refunds
 #[account]
 #[derive(InitSpace)]
 pub struct Entry {
     pub raffle: Pubkey,
     pub buyer: Pubkey,
-    pub start_ticket_index: u32,
+    /// All tickets held, bought and granted
     pub num_tickets: u32,
+    /// Tickets granted by the authority; never refunded
+    pub granted_tickets: u32,
+    /// Ticket tokens this wallet registered for the draw
+    pub registered_tickets: u32,
+    /// Number of `TicketBatch` accounts owned by this buyer
+    pub num_batches: u32,
     pub is_initialized: bool,
+    pub bump: u8,
+}
+
+/// A contiguous ticket range from a single purchase: tickets
+/// `start_ticket_index..start_ticket_index + num_tickets` belong to `buyer`
+#[account]
+#[derive(InitSpace)]
+pub struct TicketBatch {
+    pub raffle: Pubkey,
+    pub buyer: Pubkey,
+    pub index: u32,
+    pub start_ticket_index: u32,
+    pub num_tickets: u32,
+    /// What the pot received for these tickets; 0 for granted and registered tickets
+    pub amount_paid: u64,
+    /// Payer or beneficiary, chosen at purchase, that refunds go to
+    pub refund_recipient: Pubkey,
+    /// Refunded, or carried into the next round by `roll_batch`
     pub refunded: bool,
     pub bump: u8,
 }
 
+impl TicketBatch {
+    /// Share of `amount_paid` covering `num_tickets` of this batch's tickets
+    pub fn paid_for(&self, num_tickets: u32) -> Result<u64> {
+        if self.num_tickets == 0 {
+            return Ok(0);
+        }
+        let amount = (self.amount_paid as u128)
+            .checked_mul(num_tickets as u128)
+            .ok_or(RaffleError::Overflow)?
+            / self.num_tickets as u128;
+        u64::try_from(amount).map_err(|_| RaffleError::Overflow.into())
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum RaffleStatus {
     Active,
@@ -645,6 +4299,48 @@ pub enum RaffleStatus {
     DrawComplete,
     Claimed,
     Cancelled,
+    /// Unclaimed prizes were swept after the claim deadline
+    Swept,
+    /// The pot missed `min_pot` and carries into the series' next round
+    RolledOver,
+}
+
+/// What the winners of a raffle receive
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum PrizeMode {
+    /// The pot minus the platform fee, split by the prize table
+    Pot,
+    /// Assets deposited by the creator; ticket revenue goes to the creator
+    Assets,
+}
+
+/// What a holder gate checks the proof-of-holding account against
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum GateKind {
+    /// Held directly: a fungible token or a single NFT mint
+    Mint,
+    /// Any NFT whose Metaplex metadata is verified in the collection
+    Collection,
+}
+
+/// What a raffle's tickets, prizes and fees are paid in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum Currency {
+    /// An SPL token held in the escrow token account
+    Spl,
+    /// Lamports held in the program-owned SOL vault
+    Native,
+}
+
+/// What happens to prizes still unclaimed at the claim deadline
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum UnclaimedPrizeMode {
+    /// Draw new winning tickets for unclaimed places with fresh randomness
+    Redraw,
+    /// Send unclaimed prizes to the platform treasury
+    SweepToPlatform,
+    /// Send unclaimed prizes to the raffle creator
+    SweepToCreator,
 }
 
 // ============================================================================
@@ -705,4 +4401,148 @@ pub enum RaffleError {
     RandomnessNotResolved,
     #[msg("Draw not committed yet")]
     DrawNotCommitted,
+    #[msg("Prize table must have 1-10 non-zero places summing to 10000 bps")]
+    InvalidPrizeTable,
+    #[msg("Not enough tickets sold to fill every prize place")]
+    NotEnoughTickets,
+    #[msg("Invalid prize place")]
+    InvalidPlace,
+    #[msg("Prize for this place already claimed")]
+    PrizeAlreadyClaimed,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Platform fee exceeds the maximum")]
+    InvalidFee,
+    #[msg("Token mint is not allowed")]
+    MintNotAllowed,
+    #[msg("Mint is already on the allowlist")]
+    MintAlreadyAllowed,
+    #[msg("Mint allowlist is full")]
+    AllowlistFull,
+    #[msg("Draw timeout is too short")]
+    InvalidDrawTimeout,
+    #[msg("Committed draw has not timed out yet")]
+    DrawNotTimedOut,
+    #[msg("Keeper reward cannot exceed the platform fee")]
+    InvalidKeeperReward,
+    #[msg("Claim window must be greater than 0")]
+    InvalidClaimWindow,
+    #[msg("Claim deadline has passed")]
+    ClaimDeadlinePassed,
+    #[msg("Claim window is still open")]
+    ClaimWindowOpen,
+    #[msg("Raffle does not redraw unclaimed prizes")]
+    RedrawNotEnabled,
+    #[msg("Raffle does not sweep unclaimed prizes")]
+    SweepNotEnabled,
+    #[msg("Sweep recipient does not match the unclaimed prize mode")]
+    InvalidSweepRecipient,
+    #[msg("Raffle is not finished")]
+    RaffleNotFinished,
+    #[msg("All of the buyer's ticket batches must be passed")]
+    MissingTicketBatches,
+    #[msg("Ticket batch does not belong to this entry")]
+    InvalidTicketBatch,
+    #[msg("Entries must be closed before the raffle")]
+    EntriesStillOpen,
+    #[msg("Escrow or vault accounts do not match the raffle currency")]
+    InvalidPotAccounts,
+    #[msg("Escrow received nothing after transfer fees")]
+    NothingReceived,
+    #[msg("Token mint has a freeze authority")]
+    MintHasFreezeAuthority,
+    #[msg("Token mint has a permanent delegate")]
+    MintHasPermanentDelegate,
+    #[msg("Token mint freezes new token accounts by default")]
+    MintDefaultFrozen,
+    #[msg("Raffle does not award deposited prize assets")]
+    NotPrizeAssetRaffle,
+    #[msg("Tickets have already been sold")]
+    TicketsAlreadySold,
+    #[msg("Prize amount must be greater than 0")]
+    InvalidPrizeAmount,
+    #[msg("Too many prize assets")]
+    TooManyPrizeAssets,
+    #[msg("No prize assets deposited")]
+    NoPrizeAssets,
+    #[msg("Invalid prize asset")]
+    InvalidPrizeAsset,
+    #[msg("Prize asset already released")]
+    PrizeAssetReleased,
+    #[msg("Recipient does not match the raffle outcome")]
+    InvalidPrizeRecipient,
+    #[msg("Prize assets must be released before closing")]
+    PrizeAssetsOutstanding,
+    #[msg("Raffle is sold out")]
+    RaffleSoldOut,
+    #[msg("Ticket supply cap must cover every prize place")]
+    InvalidMaxTickets,
+    #[msg("Raffle has not started yet")]
+    RaffleNotStarted,
+    #[msg("Start time must be before end time")]
+    InvalidStartTime,
+    #[msg("Soft close needs a positive window and extension and a hard maximum >= end time")]
+    InvalidSoftClose,
+    #[msg("Only a later end time or a lower minimum pot can change after tickets are sold")]
+    RaffleUpdateLocked,
+    #[msg("Metadata URI too long")]
+    InvalidMetadataUri,
+    #[msg("Invalid pricing schedule")]
+    InvalidPricingSchedule,
+    #[msg("Buyer is not on the raffle allowlist")]
+    NotAllowlisted,
+    #[msg("Buyer does not hold enough of the gate token or collection")]
+    HolderGateFailed,
+    #[msg("Holder gate needs a positive minimum balance")]
+    InvalidHolderGate,
+    #[msg("Granted ticket cap exceeded")]
+    GrantCapExceeded,
+    #[msg("Refund recipient does not match the entry")]
+    RefundRecipientMismatch,
+    #[msg("Tickets in gated raffles cannot be transferred")]
+    TicketsNotTransferable,
+    #[msg("Cannot transfer tickets to yourself")]
+    InvalidTransferRecipient,
+    #[msg("Ticket batch or ticket token accounts don't match the raffle's ticket mode")]
+    InvalidTicketAccounts,
+    #[msg("Not available for ticket-token raffles")]
+    TicketTokenRaffle,
+    #[msg("Raffle does not use ticket tokens")]
+    NotTicketTokenRaffle,
+    #[msg("Ticket tokens can still be registered for the draw")]
+    RegistrationOpen,
+    #[msg("This raffle does not allow withdrawals")]
+    WithdrawalsDisabled,
+    #[msg("Too many withdrawn ticket ranges")]
+    TooManyWithdrawals,
+    #[msg("Withdraw penalty must be between 1% and 100%")]
+    InvalidWithdrawPenalty,
+    #[msg("The series' current round has not settled")]
+    RoundStillOpen,
+    #[msg("Previous round does not match the series")]
+    InvalidPreviousRound,
+    #[msg("Rollover needs a series round with a pot prize and a positive grace window")]
+    InvalidRollover,
+    #[msg("This raffle does not roll over")]
+    RolloverDisabled,
+    #[msg("The pot met its threshold")]
+    PotThresholdMet,
+    #[msg("Buyers can still opt out of the rollover")]
+    RolloverGraceActive,
+    #[msg("The rollover grace window has ended and the next round can take this batch")]
+    RolloverGraceEnded,
+    #[msg("Not the round this raffle rolled over into")]
+    InvalidRolloverTarget,
+    #[msg("Ticket tokens must be refunded before closing")]
+    TicketRefundsOutstanding,
+    #[msg("Mint is already exempt from screening")]
+    MintAlreadyExempt,
+    #[msg("Mint is not exempt from screening")]
+    MintNotExempt,
+    #[msg("Screening exemption list is full")]
+    ScreenExemptionsFull,
+    #[msg("Purchase costs more than the buyer's maximum")]
+    CostExceedsMax,
+    #[msg("Registration window must be positive")]
+    InvalidRegistrationWindow,
 }
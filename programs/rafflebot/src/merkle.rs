@@ -0,0 +1,136 @@
+//! Merkle allowlist for gated raffles.
+//!
+//! Each leaf commits to a buyer and their ticket allowance (0 falls back to the
+//! raffle's `max_per_wallet`). Leaves and nodes are hashed with distinct prefixes
+//! so an inner node can never pass as a leaf, and sibling pairs are sorted before
+//! hashing so a proof is just the list of siblings from leaf to root.
+
+use anchor_lang::prelude::Pubkey;
+use solana_sha256_hasher::hashv;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hash of one allowlist entry
+pub fn leaf(buyer: &Pubkey, allowance: u32) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, buyer.as_ref(), &allowance.to_le_bytes()]).to_bytes()
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+/// True if `proof` links `leaf` to `root`
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+/// Off-chain helper that builds the allowlist tree and its proofs.
+/// An odd node at the end of a layer is carried up unchanged.
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Build a tree over `(buyer, allowance)` entries, in the given order
+    pub fn new(entries: &[(Pubkey, u32)]) -> Self {
+        let mut layers = vec![entries
+            .iter()
+            .map(|(buyer, allowance)| leaf(buyer, *allowance))
+            .collect::<Vec<_>>()];
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// Root to pass as `allowlist_root`; all zeroes for an empty tree
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Proof for the entry at `index`, or `None` if out of range
+    pub fn proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers.first()?.len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        let mut index = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(n: usize) -> Vec<(Pubkey, u32)> {
+        (0..n).map(|i| (Pubkey::new_unique(), i as u32)).collect()
+    }
+
+    #[test]
+    fn proofs_verify_for_every_entry() {
+        // Odd sizes leave a node to carry up on one or more layers
+        for n in [1, 2, 3, 4, 5, 6, 7, 8, 9, 17] {
+            let entries = entries(n);
+            let tree = MerkleTree::new(&entries);
+            let root = tree.root();
+            for (index, (buyer, allowance)) in entries.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(verify(&root, leaf(buyer, *allowance), &proof), "n = {n}, index = {index}");
+            }
+            assert_eq!(tree.proof(n), None);
+        }
+    }
+
+    #[test]
+    fn single_entry_root_is_its_leaf() {
+        let buyer = Pubkey::new_unique();
+        let tree = MerkleTree::new(&[(buyer, 3)]);
+        assert_eq!(tree.root(), leaf(&buyer, 3));
+        assert_eq!(tree.proof(0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = MerkleTree::new(&[]);
+        assert_eq!(tree.root(), [0; 32]);
+        assert_eq!(tree.proof(0), None);
+        assert!(!verify(&tree.root(), leaf(&Pubkey::new_unique(), 0), &[]));
+    }
+
+    #[test]
+    fn rejects_a_wrong_leaf_or_allowance() {
+        let entries = entries(5);
+        let tree = MerkleTree::new(&entries);
+        let root = tree.root();
+        let (buyer, allowance) = entries[2];
+        let proof = tree.proof(2).unwrap();
+
+        assert!(!verify(&root, leaf(&buyer, allowance + 1), &proof));
+        assert!(!verify(&root, leaf(&Pubkey::new_unique(), allowance), &proof));
+        assert!(!verify(&root, leaf(&buyer, allowance), &tree.proof(3).unwrap()));
+    }
+}
@@ -1,15 +1,27 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Rafflebot } from "../target/types/rafflebot";
+import { TransferHookCounter } from "../target/types/transfer_hook_counter";
 import {
   createMint,
   createAccount,
   mintTo,
   getAccount,
+  transfer,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeTransferHookInstruction,
+  createInitializePermanentDelegateInstruction,
+  createInitializeDefaultAccountStateInstruction,
+  AccountState,
+  ExtensionType,
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
 } from "@solana/spl-token";
 import * as sb from "@switchboard-xyz/on-demand";
 import { expect } from "chai";
+import { createHash } from "crypto";
 
 function findRafflePda(
   program: Program<Rafflebot>,
@@ -22,6 +34,30 @@ function findRafflePda(
   );
 }
 
+function findSeriesPda(
+  program: Program<Rafflebot>,
+  authority: anchor.web3.PublicKey,
+  name: string
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("series"), authority.toBuffer(), Buffer.from(name)],
+    program.programId
+  );
+}
+
+function findRoundPda(
+  program: Program<Rafflebot>,
+  series: anchor.web3.PublicKey,
+  round: number
+) {
+  const roundLe = Buffer.alloc(4);
+  roundLe.writeUInt32LE(round);
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("raffle"), series.toBuffer(), roundLe],
+    program.programId
+  );
+}
+
 function findEscrowPda(
   program: Program<Rafflebot>,
   raffle: anchor.web3.PublicKey
@@ -32,6 +68,16 @@ function findEscrowPda(
   );
 }
 
+function findVaultPda(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), raffle.toBuffer()],
+    program.programId
+  );
+}
+
 function findEntryPda(
   program: Program<Rafflebot>,
   raffle: anchor.web3.PublicKey,
@@ -43,12 +89,170 @@ function findEntryPda(
   );
 }
 
+function findConfigPda(program: Program<Rafflebot>) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+}
+
+const DRAW_TIMEOUT_SLOTS = 750;
+/// `max_total_cost` for purchases that accept whatever the raffle charges
+const ANY_COST = new anchor.BN("18446744073709551615");
+
+/// The config is a singleton: initialize it once (payer must be the upgrade authority)
+async function ensureConfig(program: Program<Rafflebot>, payer: anchor.web3.Keypair) {
+  const [configPda] = findConfigPda(program);
+  const existing = await program.account.config.fetchNullable(configPda);
+  if (existing) return existing;
+
+  const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  );
+  await program.methods
+    .initializeConfig(payer.publicKey, 1000, new anchor.BN(DRAW_TIMEOUT_SLOTS), 100)
+    .accounts({
+      config: configPda,
+      program: program.programId,
+      programData,
+      admin: payer.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+  return program.account.config.fetch(configPda);
+}
+
+function findBatchPda(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey,
+  index: number
+) {
+  const indexBuf = Buffer.alloc(4);
+  indexBuf.writeUInt32LE(index);
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("batch"), raffle.toBuffer(), indexBuf],
+    program.programId
+  );
+}
+
+function findTicketMintPda(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("ticket_mint"), raffle.toBuffer()],
+    program.programId
+  );
+}
+
+// Batches owned by `owner`, in purchase order
+async function batchesOf(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey,
+  owner: anchor.web3.PublicKey
+) {
+  const batches = await program.account.ticketBatch.all([
+    { memcmp: { offset: 8, bytes: raffle.toBase58() } },
+    { memcmp: { offset: 40, bytes: owner.toBase58() } },
+  ]);
+  return batches
+    .sort((a, b) => a.account.index - b.account.index)
+    .map((batch) => batch.publicKey);
+}
+
+async function nextBatchPda(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey
+) {
+  const { numBatches } = await program.account.raffle.fetch(raffle);
+  return findBatchPda(program, raffle, numBatches)[0];
+}
+
+async function findWinningBatch(
+  program: Program<Rafflebot>,
+  raffle: anchor.web3.PublicKey,
+  ticket: number
+) {
+  const { numBatches } = await program.account.raffle.fetch(raffle);
+  for (let i = 0; i < numBatches; i++) {
+    const [batchPda] = findBatchPda(program, raffle, i);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    if (
+      ticket >= batch.startTicketIndex &&
+      ticket < batch.startTicketIndex + batch.numTickets
+    ) {
+      return { batchPda, buyer: batch.buyer };
+    }
+  }
+  throw new Error(`No batch holds ticket ${ticket}`);
+}
+
+function raffleOptions(overrides: Record<string, any> = {}) {
+  return {
+    prizeTiers: [10_000],
+    claimWindow: new anchor.BN(86_400),
+    unclaimedMode: { sweepToPlatform: {} },
+    prizeMode: { pot: {} },
+    maxTickets: 0,
+    maxGrantedTickets: 0,
+    startTime: null,
+    softClose: null,
+    metadataUri: "",
+    pricing: { bulkTiers: [], timeTiers: [] },
+    allowlistRoot: null,
+    holderGate: null,
+    withdrawPenaltyBps: null,
+    rolloverGrace: null,
+    registrationWindow: null,
+    ...overrides,
+  };
+}
+
+// Mirrors `merkle::MerkleTree` in the program crate
+function allowlistLeaf(buyer: anchor.web3.PublicKey, allowance: number): Buffer {
+  const allowanceLe = Buffer.alloc(4);
+  allowanceLe.writeUInt32LE(allowance);
+  return createHash("sha256")
+    .update(Buffer.from([0]))
+    .update(buyer.toBuffer())
+    .update(allowanceLe)
+    .digest();
+}
+
+function hashPair(a: Buffer, b: Buffer): Buffer {
+  const [first, second] = Buffer.compare(a, b) <= 0 ? [a, b] : [b, a];
+  return createHash("sha256").update(Buffer.from([1])).update(first).update(second).digest();
+}
+
+function buildAllowlist(entries: { buyer: anchor.web3.PublicKey; allowance: number }[]) {
+  const layers = [entries.map((e) => allowlistLeaf(e.buyer, e.allowance))];
+  while (layers[layers.length - 1].length > 1) {
+    const layer = layers[layers.length - 1];
+    const next: Buffer[] = [];
+    for (let i = 0; i < layer.length; i += 2) {
+      next.push(i + 1 < layer.length ? hashPair(layer[i], layer[i + 1]) : layer[i]);
+    }
+    layers.push(next);
+  }
+  const proof = (index: number) => {
+    const siblings: number[][] = [];
+    for (const layer of layers.slice(0, -1)) {
+      const sibling = layer[index ^ 1];
+      if (sibling) siblings.push(Array.from(sibling));
+      index = Math.floor(index / 2);
+    }
+    return siblings;
+  };
+  return { root: Array.from(layers[layers.length - 1][0]), proof };
+}
+
 const sleep = (ms: number) => new Promise((r) => setTimeout(r, ms));
 
-async function runVrfDraw(
+async function commitVrfDraw(
   program: Program<Rafflebot>,
   provider: anchor.AnchorProvider,
-  authority: anchor.web3.Keypair,
+  keeper: anchor.web3.Keypair,
   rafflePda: anchor.web3.PublicKey
 ) {
   const connection = provider.connection;
@@ -66,8 +270,8 @@ async function runVrfDraw(
   const createTx = await sb.asV0Tx({
     connection,
     ixs: [createIx],
-    payer: authority.publicKey,
-    signers: [authority, rngKp],
+    payer: keeper.publicKey,
+    signers: [keeper, rngKp],
     computeUnitPrice: 75_000,
     computeUnitLimitMultiple: 1.3,
   });
@@ -79,7 +283,7 @@ async function runVrfDraw(
     .commitDraw()
     .accounts({
       raffle: rafflePda,
-      authority: authority.publicKey,
+      keeper: keeper.publicKey,
       randomnessAccount: rngKp.publicKey,
     })
     .instruction();
@@ -87,14 +291,32 @@ async function runVrfDraw(
   const commitTx = await sb.asV0Tx({
     connection,
     ixs: [commitIx, commitDrawIx],
-    payer: authority.publicKey,
-    signers: [authority],
+    payer: keeper.publicKey,
+    signers: [keeper],
     computeUnitPrice: 75_000,
     computeUnitLimitMultiple: 1.3,
   });
   const commitSig = await connection.sendTransaction(commitTx);
   await connection.confirmTransaction(commitSig, "confirmed");
 
+  return { randomness, rngKp };
+}
+
+async function runVrfDraw(
+  program: Program<Rafflebot>,
+  provider: anchor.AnchorProvider,
+  keeper: anchor.web3.Keypair,
+  rafflePda: anchor.web3.PublicKey,
+  keeperTokenAccount: anchor.web3.PublicKey | null
+) {
+  const connection = provider.connection;
+  const { randomness, rngKp } = await commitVrfDraw(
+    program,
+    provider,
+    keeper,
+    rafflePda
+  );
+
   let revealIx: anchor.web3.TransactionInstruction | null = null;
   for (let attempt = 1; attempt <= 6; attempt++) {
     try {
@@ -107,20 +329,28 @@ async function runVrfDraw(
   }
   if (!revealIx) throw new Error("Failed to get reveal instruction");
 
+  // Native SOL raffles pay the keeper from the vault instead of the escrow
+  const { tokenMint, currency } = await program.account.raffle.fetch(rafflePda);
+  const native = "native" in currency;
   const settleDrawIx = await program.methods
     .settleDraw()
     .accounts({
       raffle: rafflePda,
-      authority: authority.publicKey,
+      keeper: keeper.publicKey,
       randomnessAccount: rngKp.publicKey,
+      escrow: native ? null : findEscrowPda(program, rafflePda)[0],
+      vault: native ? findVaultPda(program, rafflePda)[0] : null,
+      keeperTokenAccount: native ? null : keeperTokenAccount,
+      tokenMint: native ? null : tokenMint,
+      tokenProgram: native ? null : TOKEN_PROGRAM_ID,
     })
     .instruction();
 
   const revealTx = await sb.asV0Tx({
     connection,
     ixs: [revealIx, settleDrawIx],
-    payer: authority.publicKey,
-    signers: [authority],
+    payer: keeper.publicKey,
+    signers: [keeper],
     computeUnitPrice: 75_000,
     computeUnitLimitMultiple: 1.3,
   });
@@ -137,12 +367,13 @@ describe("rafflebot - full lifecycle", () => {
   const payer = (provider.wallet as anchor.Wallet).payer;
 
   let tokenMint: anchor.web3.PublicKey;
-  let platformWallet: anchor.web3.Keypair;
   let platformTokenAccount: anchor.web3.PublicKey;
   let buyer1: anchor.web3.Keypair;
   let buyer1TokenAccount: anchor.web3.PublicKey;
   let buyer2: anchor.web3.Keypair;
   let buyer2TokenAccount: anchor.web3.PublicKey;
+  let keeper: anchor.web3.Keypair;
+  let keeperTokenAccount: anchor.web3.PublicKey;
 
   const TICKET_PRICE = 5_000_000; // 5 USDC
   const MIN_POT = 20_000_000; // 20 USDC
@@ -156,16 +387,17 @@ describe("rafflebot - full lifecycle", () => {
   before(async () => {
     tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
 
-    platformWallet = anchor.web3.Keypair.generate();
+    const { treasury } = await ensureConfig(program, payer);
     platformTokenAccount = await createAccount(
       provider.connection,
       payer,
       tokenMint,
-      platformWallet.publicKey
+      treasury
     );
 
     buyer1 = anchor.web3.Keypair.generate();
     buyer2 = anchor.web3.Keypair.generate();
+    keeper = anchor.web3.Keypair.generate();
 
     await provider.connection.requestAirdrop(
       buyer1.publicKey,
@@ -175,8 +407,19 @@ describe("rafflebot - full lifecycle", () => {
       buyer2.publicKey,
       2 * anchor.web3.LAMPORTS_PER_SOL
     );
+    await provider.connection.requestAirdrop(
+      keeper.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
     await sleep(1000);
 
+    keeperTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      keeper.publicKey
+    );
+
     buyer1TokenAccount = await createAccount(
       provider.connection,
       payer,
@@ -212,7 +455,7 @@ describe("rafflebot - full lifecycle", () => {
   });
 
   it("creates a raffle", async () => {
-    endTime = Math.floor(Date.now() / 1000) + 8;
+    endTime = Math.floor(Date.now() / 1000) + 20;
 
     await program.methods
       .createRaffle(
@@ -220,13 +463,14 @@ describe("rafflebot - full lifecycle", () => {
         new anchor.BN(TICKET_PRICE),
         new anchor.BN(MIN_POT),
         MAX_PER_WALLET,
-        new anchor.BN(endTime)
+        new anchor.BN(endTime),
+        raffleOptions()
       )
       .accounts({
         raffle: rafflePda,
         escrow: escrowPda,
         tokenMint,
-        platformWallet: platformWallet.publicKey,
+        config: findConfigPda(program)[0],
         authority: payer.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -246,14 +490,16 @@ describe("rafflebot - full lifecycle", () => {
     const [entry2Pda] = findEntryPda(program, rafflePda, buyer2.publicKey);
 
     await program.methods
-      .buyTickets(3)
+      .buyTickets(3, ANY_COST, null, false)
       .accounts({
         raffle: rafflePda,
         entry: entry1Pda,
+        batch: await nextBatchPda(program, rafflePda),
         escrow: escrowPda,
         buyerTokenAccount: buyer1TokenAccount,
         tokenMint,
         buyer: buyer1.publicKey,
+        beneficiary: buyer1.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -261,14 +507,16 @@ describe("rafflebot - full lifecycle", () => {
       .rpc();
 
     await program.methods
-      .buyTickets(5)
+      .buyTickets(5, ANY_COST, null, false)
       .accounts({
         raffle: rafflePda,
         entry: entry2Pda,
+        batch: await nextBatchPda(program, rafflePda),
         escrow: escrowPda,
         buyerTokenAccount: buyer2TokenAccount,
         tokenMint,
         buyer: buyer2.publicKey,
+        beneficiary: buyer2.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -280,13 +528,64 @@ describe("rafflebot - full lifecycle", () => {
     expect(raffle.totalPot.toNumber()).to.equal(TICKET_PRICE * 8);
   });
 
+  it("keeps ticket ranges disjoint when buyers interleave repeat purchases", async () => {
+    const [entry1Pda] = findEntryPda(program, rafflePda, buyer1.publicKey);
+
+    // buyer1 buys again after buyer2: the new tickets must start after buyer2's
+    await program.methods
+      .buyTickets(2, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entry1Pda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: buyer1TokenAccount,
+        tokenMint,
+        buyer: buyer1.publicKey,
+        beneficiary: buyer1.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer1])
+      .rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.numBatches).to.equal(3);
+    expect(raffle.totalTickets).to.equal(10);
+
+    const expected = [
+      { buyer: buyer1.publicKey, start: 0, count: 3 },
+      { buyer: buyer2.publicKey, start: 3, count: 5 },
+      { buyer: buyer1.publicKey, start: 8, count: 2 },
+    ];
+    for (let i = 0; i < expected.length; i++) {
+      const [batchPda] = findBatchPda(program, rafflePda, i);
+      const batch = await program.account.ticketBatch.fetch(batchPda);
+      expect(batch.buyer.toBase58()).to.equal(expected[i].buyer.toBase58());
+      expect(batch.startTicketIndex).to.equal(expected[i].start);
+      expect(batch.numTickets).to.equal(expected[i].count);
+    }
+
+    // Every ticket resolves to exactly the buyer whose purchase created it
+    for (let ticket = 0; ticket < raffle.totalTickets; ticket++) {
+      const { buyer } = await findWinningBatch(program, rafflePda, ticket);
+      const owner = expected.find(
+        (e) => ticket >= e.start && ticket < e.start + e.count
+      )!.buyer;
+      expect(buyer.toBase58()).to.equal(owner.toBase58());
+    }
+
+    const entry1 = await program.account.entry.fetch(entry1Pda);
+    expect(entry1.numTickets).to.equal(5);
+  });
+
   it("fails to commit draw before deadline", async () => {
     try {
       await program.methods
         .commitDraw()
         .accounts({
           raffle: rafflePda,
-          authority: payer.publicKey,
+          keeper: payer.publicKey,
           randomnessAccount: anchor.web3.SystemProgram.programId,
         })
         .rpc();
@@ -296,38 +595,61 @@ describe("rafflebot - full lifecycle", () => {
     }
   });
 
-  it("draws winner via VRF and allows auto-payout without winner signer", async () => {
+  it("a third-party keeper draws the winner via VRF and earns the keeper reward", async () => {
     const now = Math.floor(Date.now() / 1000);
     if (now < endTime) {
       await sleep((endTime - now + 2) * 1000);
     }
 
-    await runVrfDraw(program, provider, payer, rafflePda);
+    await runVrfDraw(program, provider, keeper, rafflePda, keeperTokenAccount);
 
     const raffleAfterDraw = await program.account.raffle.fetch(rafflePda);
     expect(raffleAfterDraw.status).to.deep.equal({ drawComplete: {} });
-    expect(raffleAfterDraw.winningTicket).to.not.equal(null);
 
-    const winningTicket = Number(raffleAfterDraw.winningTicket);
-    const [entry1Pda] = findEntryPda(program, rafflePda, buyer1.publicKey);
-    const [entry2Pda] = findEntryPda(program, rafflePda, buyer2.publicKey);
-    const entry1 = await program.account.entry.fetch(entry1Pda);
-    const entry2 = await program.account.entry.fetch(entry2Pda);
+    const totalPot = TICKET_PRICE * 10; // 50 USDC
+    const expectedReward = Math.floor((totalPot * raffleAfterDraw.keeperRewardBps) / 10_000);
+    const keeperAfter = await getAccount(provider.connection, keeperTokenAccount);
+    expect(Number(keeperAfter.amount)).to.equal(expectedReward);
+    expect(raffleAfterDraw.keeperReward.toNumber()).to.equal(expectedReward);
+  });
 
-    let winnerPubkey: anchor.web3.PublicKey;
-    let winnerEntryPda: anchor.web3.PublicKey;
-    let winnerTokenAccount: anchor.web3.PublicKey;
-    if (
-      winningTicket >= entry1.startTicketIndex &&
-      winningTicket < entry1.startTicketIndex + entry1.numTickets
-    ) {
-      winnerPubkey = buyer1.publicKey;
-      winnerEntryPda = entry1Pda;
-      winnerTokenAccount = buyer1TokenAccount;
-    } else {
-      winnerPubkey = buyer2.publicKey;
-      winnerEntryPda = entry2Pda;
-      winnerTokenAccount = buyer2TokenAccount;
+  it("pays out the winner without a winner signature", async () => {
+    const raffleAfterDraw = await program.account.raffle.fetch(rafflePda);
+    expect(raffleAfterDraw.prizes[0].winningTicket).to.not.equal(null);
+
+    const winningTicket = Number(raffleAfterDraw.prizes[0].winningTicket);
+    const { batchPda: winningBatchPda, buyer: winnerPubkey } = await findWinningBatch(
+      program,
+      rafflePda,
+      winningTicket
+    );
+    const winnerTokenAccount = winnerPubkey.equals(buyer1.publicKey)
+      ? buyer1TokenAccount
+      : buyer2TokenAccount;
+
+    // A batch that does not hold the winning ticket is rejected, even for the same buyer
+    for (let i = 0; i < 3; i++) {
+      const [otherBatchPda] = findBatchPda(program, rafflePda, i);
+      if (otherBatchPda.equals(winningBatchPda)) continue;
+      try {
+        await program.methods
+          .claimPrize(0)
+          .accounts({
+            raffle: rafflePda,
+            winningBatch: otherBatchPda,
+            escrow: escrowPda,
+            winnerTokenAccount,
+            platformTokenAccount,
+            config: findConfigPda(program)[0],
+            tokenMint,
+            winner: winnerPubkey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+        expect.fail("Should have thrown");
+      } catch (e: any) {
+        expect(e.message).to.include("NotWinner");
+      }
     }
 
     const winnerBefore = await getAccount(provider.connection, winnerTokenAccount);
@@ -335,13 +657,14 @@ describe("rafflebot - full lifecycle", () => {
 
     // Auto-payout style: authority pays out; winner does not sign this transaction.
     await program.methods
-      .claimPrize()
+      .claimPrize(0)
       .accounts({
         raffle: rafflePda,
-        winnerEntry: winnerEntryPda,
+        winningBatch: winningBatchPda,
         escrow: escrowPda,
         winnerTokenAccount,
         platformTokenAccount,
+        config: findConfigPda(program)[0],
         tokenMint,
         winner: winnerPubkey,
         tokenProgram: TOKEN_PROGRAM_ID,
@@ -351,9 +674,10 @@ describe("rafflebot - full lifecycle", () => {
     const winnerAfter = await getAccount(provider.connection, winnerTokenAccount);
     const platformAfter = await getAccount(provider.connection, platformTokenAccount);
 
-    const totalPot = TICKET_PRICE * 8; // 40 USDC
-    const expectedFee = totalPot * 0.1; // 4 USDC
-    const expectedPrize = totalPot - expectedFee; // 36 USDC
+    const totalPot = TICKET_PRICE * 10; // 50 USDC
+    const fee = (totalPot * raffleAfterDraw.feeBps) / 10_000;
+    const expectedFee = fee - raffleAfterDraw.keeperReward.toNumber();
+    const expectedPrize = totalPot - fee;
 
     expect(Number(winnerAfter.amount) - Number(winnerBefore.amount)).to.equal(
       expectedPrize
@@ -364,151 +688,4283 @@ describe("rafflebot - full lifecycle", () => {
 
     const raffleAfterClaim = await program.account.raffle.fetch(rafflePda);
     expect(raffleAfterClaim.status).to.deep.equal({ claimed: {} });
-    expect(raffleAfterClaim.winner?.toBase58()).to.equal(winnerPubkey.toBase58());
+    expect(raffleAfterClaim.prizes[0].winner?.toBase58()).to.equal(winnerPubkey.toBase58());
+  });
+
+  it("cannot close the raffle while entries are still open", async () => {
+    try {
+      await program.methods
+        .closeRaffle()
+        .accounts({
+          raffle: rafflePda,
+          escrow: escrowPda,
+          platformTokenAccount,
+          config: findConfigPda(program)[0],
+          tokenMint,
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("EntriesStillOpen");
+    }
+  });
+
+  it("closes entries, batches, escrow and raffle to reclaim rent", async () => {
+    const buyerBatches = [
+      { buyer: buyer1, batches: [0, 2] },
+      { buyer: buyer2, batches: [1] },
+    ];
+
+    for (const { buyer, batches } of buyerBatches) {
+      const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+      const lamportsBefore = await provider.connection.getBalance(buyer.publicKey);
+
+      // Anyone can crank this; rent goes back to the buyer
+      await program.methods
+        .closeEntry()
+        .accounts({ raffle: rafflePda, entry: entryPda, buyer: buyer.publicKey })
+        .remainingAccounts(
+          batches.map((i) => ({
+            pubkey: findBatchPda(program, rafflePda, i)[0],
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+
+      const lamportsAfter = await provider.connection.getBalance(buyer.publicKey);
+      expect(lamportsAfter).to.be.greaterThan(lamportsBefore);
+      expect(await provider.connection.getAccountInfo(entryPda)).to.equal(null);
+    }
+
+    await program.methods
+      .closeRaffle()
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        platformTokenAccount,
+        config: findConfigPda(program)[0],
+        tokenMint,
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    expect(await provider.connection.getAccountInfo(rafflePda)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.equal(null);
   });
 });
 
-describe("rafflebot - cancellation with full refund", () => {
+describe("rafflebot - multi-winner prize table", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
   const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
   const payer = (provider.wallet as anchor.Wallet).payer;
 
   let tokenMint: anchor.web3.PublicKey;
-  let buyer: anchor.web3.Keypair;
-  let buyerTokenAccount: anchor.web3.PublicKey;
+  let platformTokenAccount: anchor.web3.PublicKey;
+  const buyers: anchor.web3.Keypair[] = [];
+  const buyerTokenAccounts: anchor.web3.PublicKey[] = [];
 
-  const RAFFLE_NAME = "cancel-full-refund-test";
-  const TICKET_PRICE = 10_000_000; // 10 USDC
-  const MIN_POT = 1_000_000_000; // Very high — won't be met
+  const RAFFLE_NAME = "multi-winner-test";
+  const TICKET_PRICE = 1_000_000; // 1 USDC
+  const PRIZE_TIERS = [5_000, 3_000, 2_000];
 
   let rafflePda: anchor.web3.PublicKey;
   let escrowPda: anchor.web3.PublicKey;
+  let endTime: number;
 
   before(async () => {
     tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
-    buyer = anchor.web3.Keypair.generate();
-    await provider.connection.requestAirdrop(
-      buyer.publicKey,
-      2 * anchor.web3.LAMPORTS_PER_SOL
-    );
-    await sleep(1000);
-
-    buyerTokenAccount = await createAccount(
-      provider.connection,
-      payer,
-      tokenMint,
-      buyer.publicKey
-    );
-    await mintTo(
+    const { treasury } = await ensureConfig(program, payer);
+    platformTokenAccount = await createAccount(
       provider.connection,
       payer,
       tokenMint,
-      buyerTokenAccount,
-      payer.publicKey,
-      100_000_000
+      treasury
     );
 
+    for (let i = 0; i < 3; i++) {
+      const buyer = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(
+        buyer.publicKey,
+        2 * anchor.web3.LAMPORTS_PER_SOL
+      );
+      buyers.push(buyer);
+    }
+    await sleep(1000);
+
+    for (const buyer of buyers) {
+      const tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        buyer.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+      buyerTokenAccounts.push(tokenAccount);
+    }
+
     [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
     [escrowPda] = findEscrowPda(program, rafflePda);
   });
 
-  it("creates raffle, buys tickets, then authority cancels", async () => {
-    const endTime = Math.floor(Date.now() / 1000) + 3600;
+  it("rejects a prize table that does not sum to 10000 bps", async () => {
+    const [badRafflePda] = findRafflePda(program, payer.publicKey, "bad-prize-table");
+    const [badEscrowPda] = findEscrowPda(program, badRafflePda);
+
+    try {
+      await program.methods
+        .createRaffle(
+          "bad-prize-table",
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          0,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          raffleOptions({ prizeTiers: [5_000, 4_000] })
+        )
+        .accounts({
+          raffle: badRafflePda,
+          escrow: badEscrowPda,
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPrizeTable");
+    }
+  });
+
+  it("draws one distinct ticket per place and pays each place its share", async () => {
+    endTime = Math.floor(Date.now() / 1000) + 8;
 
     await program.methods
       .createRaffle(
         RAFFLE_NAME,
         new anchor.BN(TICKET_PRICE),
-        new anchor.BN(MIN_POT),
-        10,
-        new anchor.BN(endTime)
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions({ prizeTiers: PRIZE_TIERS })
       )
       .accounts({
         raffle: rafflePda,
         escrow: escrowPda,
         tokenMint,
-        platformWallet: payer.publicKey,
+        config: findConfigPda(program)[0],
         authority: payer.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
-    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    for (let i = 0; i < buyers.length; i++) {
+      const [entryPda] = findEntryPda(program, rafflePda, buyers[i].publicKey);
+      await program.methods
+        .buyTickets(2, ANY_COST, null, false)
+        .accounts({
+          raffle: rafflePda,
+          entry: entryPda,
+          batch: await nextBatchPda(program, rafflePda),
+          escrow: escrowPda,
+          buyerTokenAccount: buyerTokenAccounts[i],
+          tokenMint,
+          buyer: buyers[i].publicKey,
+          beneficiary: buyers[i].publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyers[i]])
+        .rpc();
+    }
 
-    await program.methods
-      .buyTickets(3)
-      .accounts({
-        raffle: rafflePda,
-        entry: entryPda,
-        escrow: escrowPda,
-        buyerTokenAccount,
-        tokenMint,
-        buyer: buyer.publicKey,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: anchor.web3.SystemProgram.programId,
-      })
-      .signers([buyer])
-      .rpc();
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime) {
+      await sleep((endTime - now + 2) * 1000);
+    }
+    // The payer is the config treasury, so the keeper reward lands in the platform account too
+    await runVrfDraw(program, provider, payer, rafflePda, platformTokenAccount);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.status).to.deep.equal({ drawComplete: {} });
+    const tickets = raffle.prizes.map((p: any) => Number(p.winningTicket));
+    expect(new Set(tickets).size).to.equal(PRIZE_TIERS.length);
+
+    const totalPot = TICKET_PRICE * 6;
+    const fee = (totalPot * raffle.feeBps) / 10_000;
+    const prizePool = totalPot - fee;
+
+    for (let place = 0; place < PRIZE_TIERS.length; place++) {
+      const { batchPda, buyer: winner } = await findWinningBatch(
+        program,
+        rafflePda,
+        tickets[place]
+      );
+      const winnerIndex = buyers.findIndex((b) => b.publicKey.equals(winner));
+      const before = await getAccount(provider.connection, buyerTokenAccounts[winnerIndex]);
+
+      await program.methods
+        .claimPrize(place)
+        .accounts({
+          raffle: rafflePda,
+          winningBatch: batchPda,
+          escrow: escrowPda,
+          winnerTokenAccount: buyerTokenAccounts[winnerIndex],
+          platformTokenAccount,
+          config: findConfigPda(program)[0],
+          tokenMint,
+          winner,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const after = await getAccount(provider.connection, buyerTokenAccounts[winnerIndex]);
+      expect(Number(after.amount) - Number(before.amount)).to.equal(
+        (prizePool * PRIZE_TIERS[place]) / 10_000
+      );
+    }
+
+    const platform = await getAccount(provider.connection, platformTokenAccount);
+    expect(Number(platform.amount)).to.equal(fee);
+
+    const raffleAfter = await program.account.raffle.fetch(rafflePda);
+    expect(raffleAfter.status).to.deep.equal({ claimed: {} });
+    const escrow = await getAccount(provider.connection, escrowPda);
+    expect(Number(escrow.amount)).to.equal(0);
+  });
+});
+
+describe("rafflebot - claim deadline and unclaimed prizes", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+  let platformTokenAccount: anchor.web3.PublicKey;
+  let creatorTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000; // 1 USDC
+  const CLAIM_WINDOW = 5;
+
+  async function createDrawnRaffle(name: string, unclaimedMode: object) {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    const endTime = Math.floor(Date.now() / 1000) + 8;
 
     await program.methods
-      .cancelRaffle()
-      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions({ claimWindow: new anchor.BN(CLAIM_WINDOW), unclaimedMode })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    await program.methods
+      .buyTickets(4, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
       .rpc();
 
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime) {
+      await sleep((endTime - now + 2) * 1000);
+    }
+    await runVrfDraw(program, provider, payer, rafflePda, platformTokenAccount);
+    await sleep((CLAIM_WINDOW + 2) * 1000);
+
+    return { rafflePda, escrowPda };
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const { treasury } = await ensureConfig(program, payer);
+    // The payer is both the config treasury and the raffle creator here
+    platformTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      treasury
+    );
+    creatorTokenAccount = platformTokenAccount;
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+  });
+
+  it("rejects late claims and sweeps the unclaimed prize to the creator", async () => {
+    const { rafflePda, escrowPda } = await createDrawnRaffle(
+      "sweep-test",
+      { sweepToCreator: {} }
+    );
     const raffle = await program.account.raffle.fetch(rafflePda);
-    expect(raffle.status).to.deep.equal({ cancelled: {} });
+    const { batchPda } = await findWinningBatch(
+      program,
+      rafflePda,
+      Number(raffle.prizes[0].winningTicket)
+    );
+
+    try {
+      await program.methods
+        .claimPrize(0)
+        .accounts({
+          raffle: rafflePda,
+          winningBatch: batchPda,
+          escrow: escrowPda,
+          winnerTokenAccount: buyerTokenAccount,
+          platformTokenAccount,
+          config: findConfigPda(program)[0],
+          tokenMint,
+          winner: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("ClaimDeadlinePassed");
+    }
+
+    await program.methods
+      .sweepUnclaimedPrizes()
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        recipient: payer.publicKey,
+        recipientTokenAccount: creatorTokenAccount,
+        platformTokenAccount,
+        config: findConfigPda(program)[0],
+        tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const swept = await program.account.raffle.fetch(rafflePda);
+    expect(swept.status).to.deep.equal({ swept: {} });
+    const escrow = await getAccount(provider.connection, escrowPda);
+    expect(Number(escrow.amount)).to.equal(0);
   });
 
-  it("refund is full amount and can be processed without buyer signature", async () => {
-    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+  it("redraws unclaimed places with fresh randomness", async () => {
+    const { rafflePda, escrowPda } = await createDrawnRaffle(
+      "redraw-test",
+      { redraw: {} }
+    );
 
-    const buyerBefore = await getAccount(provider.connection, buyerTokenAccount);
+    try {
+      await program.methods
+        .sweepUnclaimedPrizes()
+        .accounts({
+          raffle: rafflePda,
+          escrow: escrowPda,
+          recipient: payer.publicKey,
+          recipientTokenAccount: platformTokenAccount,
+          platformTokenAccount,
+          config: findConfigPda(program)[0],
+          tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("SweepNotEnabled");
+    }
+
+    const before = await program.account.raffle.fetch(rafflePda);
+    await runVrfDraw(program, provider, payer, rafflePda, platformTokenAccount);
+
+    const after = await program.account.raffle.fetch(rafflePda);
+    expect(after.status).to.deep.equal({ drawComplete: {} });
+    expect(after.redrawCount).to.equal(1);
+    expect(after.claimDeadline!.toNumber()).to.be.greaterThan(
+      before.claimDeadline!.toNumber()
+    );
+    // The keeper reward is only paid on the first draw
+    expect(after.keeperReward.toNumber()).to.equal(before.keeperReward.toNumber());
+  });
+});
+
+describe("rafflebot - stuck draw recovery", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+  let stranger: anchor.web3.Keypair;
+
+  const RAFFLE_NAME = "stuck-draw-test";
+  const TICKET_PRICE = 1_000_000; // 1 USDC
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+    buyer = anchor.web3.Keypair.generate();
+    stranger = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.requestAirdrop(
+      stranger.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+  });
+
+  it("commits a draw that is never settled", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 8;
 
-    // Automation style: authority processes refund for the buyer.
     await program.methods
-      .claimRefund()
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    await program.methods
+      .buyTickets(2, ANY_COST, null, false)
       .accounts({
         raffle: rafflePda,
         entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
         escrow: escrowPda,
         buyerTokenAccount,
         tokenMint,
         buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
       })
+      .signers([buyer])
       .rpc();
 
-    const buyerAfter = await getAccount(provider.connection, buyerTokenAccount);
-    const gross = TICKET_PRICE * 3; // 30 USDC
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime) {
+      await sleep((endTime - now + 2) * 1000);
+    }
+    await commitVrfDraw(program, provider, payer, rafflePda);
 
-    expect(Number(buyerAfter.amount) - Number(buyerBefore.amount)).to.equal(gross);
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.status).to.deep.equal({ drawCommitted: {} });
+  });
 
-    const entry = await program.account.entry.fetch(entryPda);
-    expect(entry.refunded).to.be.true;
+  it("cannot recover the draw before the timeout", async () => {
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    try {
+      await program.methods
+        .recommitDraw()
+        .accounts({
+          raffle: rafflePda,
+          keeper: stranger.publicKey,
+          randomnessAccount: anchor.web3.SystemProgram.programId,
+          previousRandomnessAccount: raffle.randomnessAccount!,
+        })
+        .signers([stranger])
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("DrawNotTimedOut");
+    }
+
+    try {
+      await program.methods
+        .cancelRaffle()
+        .accounts({ raffle: rafflePda, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("CannotCancel");
+    }
+  });
+
+  it("anyone can cancel once the draw times out, and refunds work", async () => {
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    const deadline = raffle.commitSlot!.toNumber() + raffle.drawTimeoutSlots.toNumber();
+    while ((await provider.connection.getSlot("confirmed")) <= deadline) {
+      await sleep(2000);
+    }
+
+    await program.methods
+      .cancelRaffle()
+      .accounts({
+        raffle: rafflePda,
+        authority: stranger.publicKey,
+        randomnessAccount: raffle.randomnessAccount!,
+      })
+      .signers([stranger])
+      .rpc();
+
+    const cancelled = await program.account.raffle.fetch(rafflePda);
+    expect(cancelled.status).to.deep.equal({ cancelled: {} });
+
+    const before = await getAccount(provider.connection, buyerTokenAccount);
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const after = await getAccount(provider.connection, buyerTokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * 2);
+  });
+});
+
+describe("rafflebot - cancellation with full refund", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const RAFFLE_NAME = "cancel-full-refund-test";
+  const TICKET_PRICE = 10_000_000; // 10 USDC
+  const MIN_POT = 1_000_000_000; // Very high — won't be met
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+  });
+
+  it("creates raffle, buys tickets, then authority cancels", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 3600;
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(MIN_POT),
+        10,
+        new anchor.BN(endTime),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+
+    await program.methods
+      .buyTickets(3, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.status).to.deep.equal({ cancelled: {} });
+  });
+
+  it("refund is full amount and can be processed without buyer signature", async () => {
+    const buyerBefore = await getAccount(provider.connection, buyerTokenAccount);
+
+    // Automation style: authority processes refund for the buyer.
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const buyerAfter = await getAccount(provider.connection, buyerTokenAccount);
+    const gross = TICKET_PRICE * 3; // 30 USDC
+
+    expect(Number(buyerAfter.amount) - Number(buyerBefore.amount)).to.equal(gross);
+
+    const [batchPda] = await batchesOf(program, rafflePda, buyer.publicKey);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    expect(batch.refunded).to.be.true;
+  });
+
+  it("cannot double-claim refund", async () => {
+    try {
+      await program.methods
+        .claimRefund()
+        .accounts({
+          raffle: rafflePda,
+          batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+          escrow: escrowPda,
+          buyerTokenAccount,
+          tokenMint,
+          refundRecipient: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("AlreadyRefunded");
+    }
+  });
+});
+
+describe("rafflebot - native SOL raffle", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let buyer1: anchor.web3.Keypair;
+  let buyer2: anchor.web3.Keypair;
+  let keeper: anchor.web3.Keypair;
+  let treasury: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 0.01 * anchor.web3.LAMPORTS_PER_SOL;
+  const MIN_POT = 0.04 * anchor.web3.LAMPORTS_PER_SOL;
+  const RAFFLE_NAME = "native-sol-test";
+  const CANCEL_NAME = "native-sol-cancel";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let vaultPda: anchor.web3.PublicKey;
+
+  before(async () => {
+    ({ treasury } = await ensureConfig(program, payer));
+
+    buyer1 = anchor.web3.Keypair.generate();
+    buyer2 = anchor.web3.Keypair.generate();
+    keeper = anchor.web3.Keypair.generate();
+
+    await provider.connection.requestAirdrop(
+      buyer1.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.requestAirdrop(
+      buyer2.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await provider.connection.requestAirdrop(
+      keeper.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [vaultPda] = findVaultPda(program, rafflePda);
+  });
+
+  async function createNativeRaffle(name: string, endTime: number) {
+    const [pda] = findRafflePda(program, payer.publicKey, name);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(MIN_POT),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: pda,
+        escrow: null,
+        tokenMint: null,
+        vault: findVaultPda(program, pda)[0],
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return pda;
+  }
+
+  async function buyNative(raffle: anchor.web3.PublicKey, buyer: anchor.web3.Keypair, n: number) {
+    const [entryPda] = findEntryPda(program, raffle, buyer.publicKey);
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle,
+        entry: entryPda,
+        batch: await nextBatchPda(program, raffle),
+        escrow: null,
+        vault: findVaultPda(program, raffle)[0],
+        buyerTokenAccount: null,
+        tokenMint: null,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+  }
+
+  it("creates a raffle with a SOL vault instead of an SPL escrow", async () => {
+    await createNativeRaffle(RAFFLE_NAME, Math.floor(Date.now() / 1000) + 15);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.currency).to.deep.equal({ native: {} });
+    expect(raffle.tokenMint.toBase58()).to.equal(anchor.web3.PublicKey.default.toBase58());
+    expect(raffle.escrow.toBase58()).to.equal(vaultPda.toBase58());
+  });
+
+  it("rejects mixing the SPL escrow with the SOL vault", async () => {
+    const name = "native-sol-mixed";
+    const [pda] = findRafflePda(program, payer.publicKey, name);
+    const tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    try {
+      await program.methods
+        .createRaffle(
+          name,
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(MIN_POT),
+          0,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          raffleOptions()
+        )
+        .accounts({
+          raffle: pda,
+          escrow: findEscrowPda(program, pda)[0],
+          tokenMint,
+          vault: findVaultPda(program, pda)[0],
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPotAccounts");
+    }
+  });
+
+  it("buys tickets in lamports", async () => {
+    const vaultBefore = await provider.connection.getBalance(vaultPda);
+
+    await buyNative(rafflePda, buyer1, 3);
+    await buyNative(rafflePda, buyer2, 2);
+
+    const vaultAfter = await provider.connection.getBalance(vaultPda);
+    expect(vaultAfter - vaultBefore).to.equal(TICKET_PRICE * 5);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalPot.toNumber()).to.equal(TICKET_PRICE * 5);
+  });
+
+  it("draws and pays the prize and fee in lamports", async () => {
+    const { endTime } = await program.account.raffle.fetch(rafflePda);
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime.toNumber()) {
+      await sleep((endTime.toNumber() - now + 2) * 1000);
+    }
+
+    await runVrfDraw(program, provider, keeper, rafflePda, null);
+
+    const raffleAfterDraw = await program.account.raffle.fetch(rafflePda);
+    expect(raffleAfterDraw.status).to.deep.equal({ drawComplete: {} });
+    expect(raffleAfterDraw.keeperReward.toNumber()).to.be.greaterThan(0);
+
+    const { batchPda, buyer: winner } = await findWinningBatch(
+      program,
+      rafflePda,
+      raffleAfterDraw.prizes[0].winningTicket!
+    );
+    const winnerBefore = await provider.connection.getBalance(winner);
+    const vaultBefore = await provider.connection.getBalance(vaultPda);
+
+    await program.methods
+      .claimPrize(0)
+      .accounts({
+        raffle: rafflePda,
+        winningBatch: batchPda,
+        escrow: null,
+        vault: vaultPda,
+        winnerTokenAccount: null,
+        platformTokenAccount: null,
+        treasury,
+        config: findConfigPda(program)[0],
+        tokenMint: null,
+        winner,
+        tokenProgram: null,
+      })
+      .rpc();
+
+    const totalPot = TICKET_PRICE * 5;
+    const fee = Math.floor((totalPot * raffleAfterDraw.feeBps) / 10_000);
+    const expectedFee = fee - raffleAfterDraw.keeperReward.toNumber();
+    const expectedPrize = totalPot - fee;
+
+    const winnerAfter = await provider.connection.getBalance(winner);
+    const vaultAfter = await provider.connection.getBalance(vaultPda);
+    expect(winnerAfter - winnerBefore).to.equal(expectedPrize);
+    expect(vaultBefore - vaultAfter).to.equal(expectedPrize + expectedFee);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.status).to.deep.equal({ claimed: {} });
+  });
+
+  it("closes the SOL vault with the raffle", async () => {
+    for (const buyer of [buyer1, buyer2]) {
+      const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+      const entry = await program.account.entry.fetch(entryPda);
+      const { numBatches } = await program.account.raffle.fetch(rafflePda);
+      const owned: anchor.web3.PublicKey[] = [];
+      for (let i = 0; i < numBatches; i++) {
+        const [batchPda] = findBatchPda(program, rafflePda, i);
+        const batch = await program.account.ticketBatch.fetchNullable(batchPda);
+        if (batch && batch.buyer.equals(buyer.publicKey)) owned.push(batchPda);
+      }
+      expect(owned.length).to.equal(entry.numBatches);
+
+      await program.methods
+        .closeEntry()
+        .accounts({ raffle: rafflePda, entry: entryPda, buyer: buyer.publicKey })
+        .remainingAccounts(
+          owned.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+    }
+
+    await program.methods
+      .closeRaffle()
+      .accounts({
+        raffle: rafflePda,
+        escrow: null,
+        vault: vaultPda,
+        platformTokenAccount: null,
+        treasury,
+        config: findConfigPda(program)[0],
+        tokenMint: null,
+        authority: payer.publicKey,
+        tokenProgram: null,
+      })
+      .rpc();
+
+    expect(await provider.connection.getAccountInfo(rafflePda)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(vaultPda)).to.equal(null);
+  });
+
+  it("refunds the full lamport amount after cancellation", async () => {
+    const cancelPda = await createNativeRaffle(
+      CANCEL_NAME,
+      Math.floor(Date.now() / 1000) + 3600
+    );
+    await buyNative(cancelPda, buyer1, 4);
+
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: cancelPda, authority: payer.publicKey })
+      .rpc();
+
+    const buyerBefore = await provider.connection.getBalance(buyer1.publicKey);
+
+    // Processed by the authority; the buyer does not sign or pay fees
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: cancelPda,
+        batch: (await batchesOf(program, cancelPda, buyer1.publicKey))[0],
+        escrow: null,
+        vault: findVaultPda(program, cancelPda)[0],
+        buyerTokenAccount: null,
+        tokenMint: null,
+        refundRecipient: buyer1.publicKey,
+        tokenProgram: null,
+      })
+      .rpc();
+
+    const buyerAfter = await provider.connection.getBalance(buyer1.publicKey);
+    expect(buyerAfter - buyerBefore).to.equal(TICKET_PRICE * 4);
+  });
+});
+
+describe("rafflebot - Token-2022 mints", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const hookProgram = anchor.workspace
+    .TransferHookCounter as Program<TransferHookCounter>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  const TICKET_PRICE = 1_000_000;
+  const FEE_BPS = 100; // 1% transfer fee
+
+  let buyer: anchor.web3.Keypair;
+
+  /// Create a Token-2022 mint with a single extension initialized by `extensionIx`
+  async function createExtensionMint(
+    extension: ExtensionType,
+    extensionIx: (mint: anchor.web3.PublicKey) => anchor.web3.TransactionInstruction
+  ) {
+    const mintKp = anchor.web3.Keypair.generate();
+    const mintLen = getMintLen([extension]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mintKp.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      extensionIx(mintKp.publicKey),
+      createInitializeMintInstruction(
+        mintKp.publicKey,
+        6,
+        payer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [mintKp]);
+    return mintKp.publicKey;
+  }
+
+  async function fundBuyer(mint: anchor.web3.PublicKey) {
+    const tokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      mint,
+      buyer.publicKey,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      mint,
+      tokenAccount,
+      payer.publicKey,
+      100_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    return tokenAccount;
+  }
+
+  async function createToken2022Raffle(name: string, mint: anchor.web3.PublicKey) {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE * 100),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint: mint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return { rafflePda, escrowPda };
+  }
+
+  before(async () => {
+    await ensureConfig(program, payer);
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+  });
+
+  it("credits the pot with what the escrow received from a transfer-fee mint", async () => {
+    const mint = await createExtensionMint(ExtensionType.TransferFeeConfig, (m) =>
+      createInitializeTransferFeeConfigInstruction(
+        m,
+        payer.publicKey,
+        payer.publicKey,
+        FEE_BPS,
+        BigInt(1_000_000_000),
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    const buyerTokenAccount = await fundBuyer(mint);
+    const { rafflePda, escrowPda } = await createToken2022Raffle("t22-transfer-fee", mint);
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+
+    await program.methods
+      .buyTickets(5, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint: mint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const gross = TICKET_PRICE * 5;
+    const net = gross - (gross * FEE_BPS) / 10_000;
+    const escrow = await getAccount(
+      provider.connection,
+      escrowPda,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(escrow.amount)).to.equal(net);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalPot.toNumber()).to.equal(net);
+    const [batchPda] = await batchesOf(program, rafflePda, buyer.publicKey);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    expect(batch.amountPaid.toNumber()).to.equal(net);
+
+    // A refund pays out exactly what the escrow received, so it never
+    // dips into tokens belonging to anyone else
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint: mint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+
+    const escrowAfter = await getAccount(
+      provider.connection,
+      escrowPda,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(escrowAfter.amount)).to.equal(0);
+  });
+
+  it("forwards transfer-hook extra accounts on deposits and payouts", async () => {
+    const mint = await createExtensionMint(ExtensionType.TransferHook, (m) =>
+      createInitializeTransferHookInstruction(
+        m,
+        payer.publicKey,
+        hookProgram.programId,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    const [extraAccountMetaList] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-account-metas"), mint.toBuffer()],
+      hookProgram.programId
+    );
+    const [counter] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("counter"), mint.toBuffer()],
+      hookProgram.programId
+    );
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        payer: payer.publicKey,
+        extraAccountMetaList,
+        mint,
+        counter,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Extra accounts the hook needs, passed as remaining accounts
+    const hookAccounts = [
+      { pubkey: counter, isWritable: true, isSigner: false },
+      { pubkey: hookProgram.programId, isWritable: false, isSigner: false },
+      { pubkey: extraAccountMetaList, isWritable: false, isSigner: false },
+    ];
+
+    const buyerTokenAccount = await fundBuyer(mint);
+    const { rafflePda, escrowPda } = await createToken2022Raffle("t22-transfer-hook", mint);
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+
+    await program.methods
+      .buyTickets(2, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint: mint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(hookAccounts)
+      .signers([buyer])
+      .rpc();
+
+    expect((await hookProgram.account.counter.fetch(counter)).transfers.toNumber()).to.equal(1);
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalPot.toNumber()).to.equal(TICKET_PRICE * 2);
+
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint: mint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookAccounts)
+      .rpc();
+
+    expect((await hookProgram.account.counter.fetch(counter)).transfers.toNumber()).to.equal(2);
+    const buyerAccount = await getAccount(
+      provider.connection,
+      buyerTokenAccount,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(buyerAccount.amount)).to.equal(100_000_000);
+  });
+});
+
+describe("rafflebot - prize-asset raffle", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let nftMint: anchor.web3.PublicKey;
+  let sponsorMint: anchor.web3.PublicKey;
+  let creatorTokenAccount: anchor.web3.PublicKey;
+  let creatorNftAccount: anchor.web3.PublicKey;
+  let creatorSponsorAccount: anchor.web3.PublicKey;
+  let platformTokenAccount: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const SPONSOR_AMOUNT = 50_000_000;
+
+  function findPrizeVaultPda(raffle: anchor.web3.PublicKey, mint: anchor.web3.PublicKey) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("prize"), raffle.toBuffer(), mint.toBuffer()],
+      program.programId
+    );
+  }
+
+  async function createAssetRaffle(name: string, endTime: number) {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions({ prizeMode: { assets: {} } })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return { rafflePda, escrowPda };
+  }
+
+  async function depositPrize(
+    rafflePda: anchor.web3.PublicKey,
+    prizeMint: anchor.web3.PublicKey,
+    authorityTokenAccount: anchor.web3.PublicKey,
+    amount: number
+  ) {
+    await program.methods
+      .depositPrize(new anchor.BN(amount))
+      .accounts({
+        raffle: rafflePda,
+        config: findConfigPda(program)[0],
+        prizeVault: findPrizeVaultPda(rafflePda, prizeMint)[0],
+        prizeMint,
+        authorityTokenAccount,
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  async function releasePrizeAsset(
+    rafflePda: anchor.web3.PublicKey,
+    index: number,
+    prizeMint: anchor.web3.PublicKey,
+    recipientTokenAccount: anchor.web3.PublicKey
+  ) {
+    await program.methods
+      .releasePrizeAsset(index)
+      .accounts({
+        raffle: rafflePda,
+        prizeVault: findPrizeVaultPda(rafflePda, prizeMint)[0],
+        prizeMint,
+        recipientTokenAccount,
+        authority: payer.publicKey,
+        config: findConfigPda(program)[0],
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  async function buy(rafflePda: anchor.web3.PublicKey, escrowPda: anchor.web3.PublicKey, n: number) {
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    nftMint = await createMint(provider.connection, payer, payer.publicKey, null, 0);
+    sponsorMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+
+    const { treasury } = await ensureConfig(program, payer);
+    platformTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      treasury,
+      anchor.web3.Keypair.generate()
+    );
+    creatorTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      payer.publicKey
+    );
+    creatorNftAccount = await createAccount(
+      provider.connection,
+      payer,
+      nftMint,
+      payer.publicKey
+    );
+    creatorSponsorAccount = await createAccount(
+      provider.connection,
+      payer,
+      sponsorMint,
+      payer.publicKey
+    );
+    await mintTo(provider.connection, payer, nftMint, creatorNftAccount, payer.publicKey, 1);
+    await mintTo(
+      provider.connection,
+      payer,
+      sponsorMint,
+      creatorSponsorAccount,
+      payer.publicKey,
+      SPONSOR_AMOUNT * 2
+    );
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+  });
+
+  it("requires a single prize place for asset raffles", async () => {
+    const name = "asset-multi-place";
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    try {
+      await program.methods
+        .createRaffle(
+          name,
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          0,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          raffleOptions({ prizeMode: { assets: {} }, prizeTiers: [5000, 5000] })
+        )
+        .accounts({
+          raffle: rafflePda,
+          escrow: findEscrowPda(program, rafflePda)[0],
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPrizeTable");
+    }
+  });
+
+  it("rejects prize mints whose freeze authority the creator holds", async () => {
+    const { rafflePda } = await createAssetRaffle(
+      "asset-frozen-prize",
+      Math.floor(Date.now() / 1000) + 3600
+    );
+    const freezableMint = await createMint(
+      provider.connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6
+    );
+    const freezableAccount = await createAccount(
+      provider.connection,
+      payer,
+      freezableMint,
+      payer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      freezableMint,
+      freezableAccount,
+      payer.publicKey,
+      SPONSOR_AMOUNT
+    );
+
+    try {
+      await depositPrize(rafflePda, freezableMint, freezableAccount, SPONSOR_AMOUNT);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintHasFreezeAuthority");
+    }
+  });
+
+  it("awards the deposited assets to the winner and revenue to the creator", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 15;
+    const { rafflePda, escrowPda } = await createAssetRaffle("asset-prize-test", endTime);
+
+    await depositPrize(rafflePda, nftMint, creatorNftAccount, 1);
+    await depositPrize(rafflePda, sponsorMint, creatorSponsorAccount, SPONSOR_AMOUNT);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.prizeAssets.length).to.equal(2);
+    expect(raffle.prizeAssets[1].amount.toNumber()).to.equal(SPONSOR_AMOUNT);
+
+    await buy(rafflePda, escrowPda, 4);
+
+    // The prize is locked in once tickets are sold
+    const lateMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const lateAccount = await createAccount(
+      provider.connection,
+      payer,
+      lateMint,
+      payer.publicKey
+    );
+    await mintTo(provider.connection, payer, lateMint, lateAccount, payer.publicKey, 1);
+    try {
+      await depositPrize(rafflePda, lateMint, lateAccount, 1);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("TicketsAlreadySold");
+    }
+
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime) {
+      await sleep((endTime - now + 2) * 1000);
+    }
+    await runVrfDraw(program, provider, payer, rafflePda, platformTokenAccount);
+
+    const drawn = await program.account.raffle.fetch(rafflePda);
+    const { batchPda, buyer: winner } = await findWinningBatch(
+      program,
+      rafflePda,
+      drawn.prizes[0].winningTicket!
+    );
+    expect(winner.toBase58()).to.equal(buyer.publicKey.toBase58());
+
+    const creatorBefore = await getAccount(provider.connection, creatorTokenAccount);
+    const winnerBefore = await getAccount(provider.connection, buyerTokenAccount);
+
+    await program.methods
+      .claimPrize(0)
+      .accounts({
+        raffle: rafflePda,
+        winningBatch: batchPda,
+        escrow: escrowPda,
+        winnerTokenAccount: buyerTokenAccount,
+        creatorTokenAccount,
+        platformTokenAccount,
+        config: findConfigPda(program)[0],
+        tokenMint,
+        winner,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const totalPot = TICKET_PRICE * 4;
+    const fee = Math.floor((totalPot * drawn.feeBps) / 10_000);
+    const creatorAfter = await getAccount(provider.connection, creatorTokenAccount);
+    const winnerAfter = await getAccount(provider.connection, buyerTokenAccount);
+    expect(Number(creatorAfter.amount) - Number(creatorBefore.amount)).to.equal(
+      totalPot - fee
+    );
+    expect(Number(winnerAfter.amount)).to.equal(Number(winnerBefore.amount));
+
+    // Anyone can release the assets to the recorded winner
+    const winnerNftAccount = await createAccount(
+      provider.connection,
+      payer,
+      nftMint,
+      buyer.publicKey
+    );
+    const winnerSponsorAccount = await createAccount(
+      provider.connection,
+      payer,
+      sponsorMint,
+      buyer.publicKey
+    );
+    try {
+      await releasePrizeAsset(rafflePda, 0, nftMint, creatorNftAccount);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPrizeRecipient");
+    }
+    await releasePrizeAsset(rafflePda, 0, nftMint, winnerNftAccount);
+    await releasePrizeAsset(rafflePda, 1, sponsorMint, winnerSponsorAccount);
+
+    expect(Number((await getAccount(provider.connection, winnerNftAccount)).amount)).to.equal(1);
+    expect(
+      Number((await getAccount(provider.connection, winnerSponsorAccount)).amount)
+    ).to.equal(SPONSOR_AMOUNT);
+    expect(
+      await provider.connection.getAccountInfo(findPrizeVaultPda(rafflePda, nftMint)[0])
+    ).to.equal(null);
+
+    const settled = await program.account.raffle.fetch(rafflePda);
+    expect(settled.prizeAssets.every((asset) => asset.released)).to.be.true;
+  });
+
+  it("returns the deposited assets to the creator on cancellation", async () => {
+    const { rafflePda, escrowPda } = await createAssetRaffle(
+      "asset-prize-cancel",
+      Math.floor(Date.now() / 1000) + 3600
+    );
+    await depositPrize(rafflePda, sponsorMint, creatorSponsorAccount, SPONSOR_AMOUNT);
+    await buy(rafflePda, escrowPda, 2);
+
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    const before = await getAccount(provider.connection, creatorSponsorAccount);
+    await releasePrizeAsset(rafflePda, 0, sponsorMint, creatorSponsorAccount);
+    const after = await getAccount(provider.connection, creatorSponsorAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(SPONSOR_AMOUNT);
+
+    // Buyers are still refunded from the escrow as usual
+    const buyerBefore = await getAccount(provider.connection, buyerTokenAccount);
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const buyerAfter = await getAccount(provider.connection, buyerTokenAccount);
+    expect(Number(buyerAfter.amount) - Number(buyerBefore.amount)).to.equal(
+      TICKET_PRICE * 2
+    );
+  });
+});
+
+describe("rafflebot - ticket supply cap", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyers: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const MAX_TICKETS = 5;
+  const RAFFLE_NAME = "supply-cap-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function buy(index: number, n: number) {
+    const { kp, tokenAccount } = buyers[index];
+    const [entryPda] = findEntryPda(program, rafflePda, kp.publicKey);
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 3; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      buyers.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const buyer of buyers) {
+      buyer.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        buyer.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        buyer.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({ maxTickets: MAX_TICKETS })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("partially fills a purchase that crosses the cap", async () => {
+    await buy(0, 3);
+
+    const before = await getAccount(provider.connection, buyers[1].tokenAccount);
+    await buy(1, 4);
+    const after = await getAccount(provider.connection, buyers[1].tokenAccount);
+
+    expect(Number(before.amount) - Number(after.amount)).to.equal(TICKET_PRICE * 2);
+    const [entryPda] = findEntryPda(program, rafflePda, buyers[1].kp.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(2);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalTickets).to.equal(MAX_TICKETS);
+  });
+
+  it("rejects purchases once sold out", async () => {
+    try {
+      await buy(2, 1);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleSoldOut");
+    }
+  });
+
+  it("commits the draw before end_time once sold out", async () => {
+    await commitVrfDraw(program, provider, payer, rafflePda);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.status).to.deep.equal({ drawCommitted: {} });
+    expect(raffle.endTime.toNumber()).to.be.greaterThan(Math.floor(Date.now() / 1000));
+  });
+});
+
+describe("rafflebot - anti-sniping soft close", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const RAFFLE_NAME = "soft-close-test";
+  const EXTENSION = 600;
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+  let endTime: number;
+  let maxEndTime: number;
+
+  async function buy() {
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    await program.methods
+      .buyTickets(1, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+    endTime = Math.floor(Date.now() / 1000) + 3600;
+    maxEndTime = endTime + EXTENSION + EXTENSION / 2;
+
+    // A window wider than the remaining time makes every buy a late buy
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(endTime),
+        raffleOptions({
+          softClose: {
+            window: new anchor.BN(7200),
+            extension: new anchor.BN(EXTENSION),
+            maxEndTime: new anchor.BN(maxEndTime),
+          },
+        })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("extends end_time on a buy inside the window", async () => {
+    await buy();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.endTime.toNumber()).to.equal(endTime + EXTENSION);
+    expect(raffle.numExtensions).to.equal(1);
+  });
+
+  it("caps extensions at the hard maximum", async () => {
+    await buy();
+    let raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.endTime.toNumber()).to.equal(maxEndTime);
+    expect(raffle.numExtensions).to.equal(2);
+
+    await buy();
+    raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.endTime.toNumber()).to.equal(maxEndTime);
+    expect(raffle.numExtensions).to.equal(2);
+  });
+
+  it("rejects a hard maximum before end_time", async () => {
+    const name = "soft-close-invalid";
+    const [pda] = findRafflePda(program, payer.publicKey, name);
+    try {
+      await program.methods
+        .createRaffle(
+          name,
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          0,
+          new anchor.BN(endTime),
+          raffleOptions({
+            softClose: {
+              window: new anchor.BN(60),
+              extension: new anchor.BN(60),
+              maxEndTime: new anchor.BN(endTime - 1),
+            },
+          })
+        )
+        .accounts({
+          raffle: pda,
+          escrow: findEscrowPda(program, pda)[0],
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidSoftClose");
+    }
+  });
+});
+
+describe("rafflebot - raffle updates", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const RAFFLE_NAME = "update-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+  let endTime: number;
+
+  function update(params: {
+    ticketPrice?: number;
+    minPot?: number;
+    maxPerWallet?: number;
+    endTime?: number;
+    metadataUri?: string;
+  }) {
+    const bn = (v?: number) => (v === undefined ? null : new anchor.BN(v));
+    return program.methods
+      .updateRaffle(
+        bn(params.ticketPrice),
+        bn(params.minPot),
+        params.maxPerWallet ?? null,
+        bn(params.endTime),
+        params.metadataUri ?? null
+      )
+      .accounts({ raffle: rafflePda, authority: payer.publicKey });
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+    endTime = Math.floor(Date.now() / 1000) + 3600;
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE * 10),
+        5,
+        new anchor.BN(endTime),
+        raffleOptions({ metadataUri: "https://example.com/raffle.json" })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("rejects updates from anyone but the authority", async () => {
+    try {
+      await program.methods
+        .updateRaffle(new anchor.BN(1), null, null, null, null)
+        .accounts({ raffle: rafflePda, authority: buyer.publicKey })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("ConstraintHasOne");
+    }
+  });
+
+  it("edits any parameter before the first sale", async () => {
+    endTime -= 600;
+    await update({
+      ticketPrice: TICKET_PRICE * 2,
+      minPot: TICKET_PRICE * 20,
+      maxPerWallet: 10,
+      endTime,
+      metadataUri: "https://example.com/v2.json",
+    }).rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.ticketPrice.toNumber()).to.equal(TICKET_PRICE * 2);
+    expect(raffle.minPot.toNumber()).to.equal(TICKET_PRICE * 20);
+    expect(raffle.maxPerWallet).to.equal(10);
+    expect(raffle.endTime.toNumber()).to.equal(endTime);
+    expect(raffle.metadataUri).to.equal("https://example.com/v2.json");
+  });
+
+  it("rejects a minimum pot below the ticket price", async () => {
+    try {
+      await update({ minPot: TICKET_PRICE }).rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MinPotTooLow");
+    }
+  });
+
+  it("locks price, wallet cap and metadata once tickets are sold", async () => {
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    await program.methods
+      .buyTickets(1, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    for (const params of [
+      { ticketPrice: TICKET_PRICE },
+      { maxPerWallet: 20 },
+      { metadataUri: "https://example.com/v3.json" },
+    ]) {
+      try {
+        await update(params).rpc();
+        expect.fail("Should have thrown");
+      } catch (e: any) {
+        expect(e.message).to.include("RaffleUpdateLocked");
+      }
+    }
+  });
+
+  it("rejects a shorter end time or a higher minimum pot after sales start", async () => {
+    for (const params of [{ endTime: endTime - 60 }, { minPot: TICKET_PRICE * 30 }]) {
+      try {
+        await update(params).rpc();
+        expect.fail("Should have thrown");
+      } catch (e: any) {
+        expect(e.message).to.include("RaffleUpdateLocked");
+      }
+    }
+  });
+
+  it("allows extending end time and lowering the minimum pot after sales start", async () => {
+    await update({ endTime: endTime + 3600, minPot: TICKET_PRICE * 5 }).rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.endTime.toNumber()).to.equal(endTime + 3600);
+    expect(raffle.minPot.toNumber()).to.equal(TICKET_PRICE * 5);
+  });
+});
+
+describe("rafflebot - pricing schedule", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const RAFFLE_NAME = "pricing-test";
+  // 10 tickets for the price of 8; +50% inside the last two hours
+  const PRICING = {
+    bulkTiers: [{ minTickets: 10, discountBps: 2_000 }],
+    timeTiers: [{ secondsBeforeEnd: new anchor.BN(7200), markupBps: 5_000 }],
+  };
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function buy(n: number, maxTotalCost = ANY_COST): Promise<number> {
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    const before = await getAccount(provider.connection, buyerTokenAccount);
+    await program.methods
+      .buyTickets(n, maxTotalCost, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+    const after = await getAccount(provider.connection, buyerTokenAccount);
+    return Number(before.amount) - Number(after.amount);
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      buyer.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+    buyerTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      buyerTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE * 100),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({ pricing: PRICING })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("applies the time-based markup close to end_time", async () => {
+    expect(await buy(1)).to.equal(TICKET_PRICE * 1.5);
+  });
+
+  it("rejects a purchase costing more than the buyer's maximum", async () => {
+    try {
+      await buy(1, new anchor.BN(TICKET_PRICE));
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("CostExceedsMax");
+    }
+  });
+
+  it("applies the bulk discount on top of the markup", async () => {
+    expect(await buy(10)).to.equal(TICKET_PRICE * 1.5 * 8);
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(11);
+    const batches = await Promise.all(
+      (await batchesOf(program, rafflePda, buyer.publicKey)).map((pda) =>
+        program.account.ticketBatch.fetch(pda)
+      )
+    );
+    const paid = batches.reduce((sum, batch) => sum + batch.amountPaid.toNumber(), 0);
+    expect(paid).to.equal(TICKET_PRICE * 13.5);
+  });
+
+  it("refunds what the buyer actually paid", async () => {
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    const before = await getAccount(provider.connection, buyerTokenAccount);
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, buyer.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount,
+        tokenMint,
+        refundRecipient: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const after = await getAccount(provider.connection, buyerTokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * 13.5);
+  });
+
+  it("rejects a bulk discount of 100%", async () => {
+    const name = "pricing-invalid";
+    const [pda] = findRafflePda(program, payer.publicKey, name);
+    try {
+      await program.methods
+        .createRaffle(
+          name,
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          0,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          raffleOptions({
+            pricing: { bulkTiers: [{ minTickets: 2, discountBps: 10_000 }], timeTiers: [] },
+          })
+        )
+        .accounts({
+          raffle: pda,
+          escrow: findEscrowPda(program, pda)[0],
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPricingSchedule");
+    }
+  });
+});
+
+describe("rafflebot - allowlist", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyers: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const MAX_PER_WALLET = 2;
+  const RAFFLE_NAME = "allowlist-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+  let allowlist: ReturnType<typeof buildAllowlist>;
+
+  async function buy(index: number, n: number, proof: any) {
+    const { kp, tokenAccount } = buyers[index];
+    const [entryPda] = findEntryPda(program, rafflePda, kp.publicKey);
+    await program.methods
+      .buyTickets(n, ANY_COST, proof, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 3; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      buyers.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const buyer of buyers) {
+      buyer.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        buyer.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        buyer.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    // Buyer 0 falls back to max_per_wallet, buyer 1 gets 5 tickets, buyer 2 is not listed
+    allowlist = buildAllowlist([
+      { buyer: buyers[0].kp.publicKey, allowance: 0 },
+      { buyer: buyers[1].kp.publicKey, allowance: 5 },
+    ]);
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        MAX_PER_WALLET,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({ allowlistRoot: allowlist.root })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("applies max_per_wallet to a leaf without an allowance", async () => {
+    const proof = { allowance: 0, proof: allowlist.proof(0) };
+    await buy(0, MAX_PER_WALLET, proof);
+    try {
+      await buy(0, 1, proof);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MaxTicketsExceeded");
+    }
+  });
+
+  it("lets a leaf allowance replace max_per_wallet", async () => {
+    await buy(1, 5, { allowance: 5, proof: allowlist.proof(1) });
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyers[1].kp.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(5);
+  });
+
+  it("rejects an inflated allowance", async () => {
+    try {
+      await buy(1, 1, { allowance: 10, proof: allowlist.proof(1) });
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("NotAllowlisted");
+    }
+  });
+
+  it("rejects buyers who are not on the allowlist", async () => {
+    for (const proof of [null, { allowance: 0, proof: allowlist.proof(0) }]) {
+      try {
+        await buy(2, 1, proof);
+        expect.fail("Should have thrown");
+      } catch (e: any) {
+        expect(e.message).to.include("NotAllowlisted");
+      }
+    }
+  });
+});
+
+describe("rafflebot - holder-gated raffle", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let gateMint: anchor.web3.PublicKey;
+  let otherMint: anchor.web3.PublicKey;
+  let buyers: {
+    kp: anchor.web3.Keypair;
+    tokenAccount: anchor.web3.PublicKey;
+    holdingAccount: anchor.web3.PublicKey;
+  }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const MIN_BALANCE = 100;
+  const RAFFLE_NAME = "holder-gate-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function buy(index: number, holdingTokenAccount: anchor.web3.PublicKey | null) {
+    const { kp, tokenAccount } = buyers[index];
+    const [entryPda] = findEntryPda(program, rafflePda, kp.publicKey);
+    await program.methods
+      .buyTickets(1, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        holdingTokenAccount,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    gateMint = await createMint(provider.connection, payer, payer.publicKey, null, 0);
+    otherMint = await createMint(provider.connection, payer, payer.publicKey, null, 0);
+    await ensureConfig(program, payer);
+
+    // Buyer 0 holds enough of the gate mint, buyer 1 too little, buyer 2 only another mint
+    const holdings = [
+      { mint: gateMint, amount: MIN_BALANCE },
+      { mint: gateMint, amount: MIN_BALANCE - 1 },
+      { mint: otherMint, amount: MIN_BALANCE },
+    ];
+    const keypairs = holdings.map(() => anchor.web3.Keypair.generate());
+    for (const kp of keypairs) {
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    }
+    await sleep(1000);
+    for (let i = 0; i < keypairs.length; i++) {
+      const kp = keypairs[i];
+      const tokenAccount = await createAccount(provider.connection, payer, tokenMint, kp.publicKey);
+      await mintTo(provider.connection, payer, tokenMint, tokenAccount, payer.publicKey, 100_000_000);
+      const holdingAccount = await createAccount(
+        provider.connection,
+        payer,
+        holdings[i].mint,
+        kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        holdings[i].mint,
+        holdingAccount,
+        payer.publicKey,
+        holdings[i].amount
+      );
+      buyers.push({ kp, tokenAccount, holdingAccount });
+    }
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({
+          holderGate: { kind: { mint: {} }, key: gateMint, minBalance: new anchor.BN(MIN_BALANCE) },
+        })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("admits a buyer holding the minimum balance", async () => {
+    await buy(0, buyers[0].holdingAccount);
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyers[0].kp.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(1);
+  });
+
+  it("rejects a buyer without a proof-of-holding account", async () => {
+    try {
+      await buy(0, null);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("HolderGateFailed");
+    }
+  });
+
+  it("rejects a balance below the minimum", async () => {
+    try {
+      await buy(1, buyers[1].holdingAccount);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("HolderGateFailed");
+    }
+  });
+
+  it("rejects a holding of the wrong mint", async () => {
+    try {
+      await buy(2, buyers[2].holdingAccount);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("HolderGateFailed");
+    }
+  });
+
+  it("rejects another wallet's holding account", async () => {
+    try {
+      await buy(1, buyers[0].holdingAccount);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("HolderGateFailed");
+    }
+  });
+});
+
+describe("rafflebot - granted tickets", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let recipient: anchor.web3.Keypair;
+  let recipientTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const MAX_PER_WALLET = 2;
+  const MAX_GRANTED = 3;
+  const RAFFLE_NAME = "grant-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function grant(n: number, authority: anchor.web3.Keypair = payer) {
+    const [entryPda] = findEntryPda(program, rafflePda, recipient.publicKey);
+    await program.methods
+      .grantTickets(n)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        recipient: recipient.publicKey,
+        authority: authority.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers(authority === payer ? [] : [authority])
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    recipient = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(
+      recipient.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await sleep(1000);
+    recipientTokenAccount = await createAccount(
+      provider.connection,
+      payer,
+      tokenMint,
+      recipient.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      recipientTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        MAX_PER_WALLET,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({ maxGrantedTickets: MAX_GRANTED })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("grants free tickets that count toward the draw", async () => {
+    await grant(2);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalTickets).to.equal(2);
+    expect(raffle.grantedTickets).to.equal(2);
+    expect(raffle.totalPot.toNumber()).to.equal(0);
+
+    const [entryPda] = findEntryPda(program, rafflePda, recipient.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(2);
+    expect(entry.grantedTickets).to.equal(2);
+    const [batchPda] = await batchesOf(program, rafflePda, recipient.publicKey);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    expect(batch.amountPaid.toNumber()).to.equal(0);
+
+    const { buyer } = await findWinningBatch(program, rafflePda, 1);
+    expect(buyer.toBase58()).to.equal(recipient.publicKey.toBase58());
+  });
+
+  it("enforces the granted ticket cap", async () => {
+    try {
+      await grant(MAX_GRANTED);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("GrantCapExceeded");
+    }
+  });
+
+  it("rejects grants from anyone but the authority", async () => {
+    try {
+      await grant(1, recipient);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("ConstraintHasOne");
+    }
+  });
+
+  it("does not count granted tickets against max_per_wallet", async () => {
+    const [entryPda] = findEntryPda(program, rafflePda, recipient.publicKey);
+    await program.methods
+      .buyTickets(MAX_PER_WALLET, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: recipientTokenAccount,
+        tokenMint,
+        buyer: recipient.publicKey,
+        beneficiary: recipient.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([recipient])
+      .rpc();
+
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(2 + MAX_PER_WALLET);
+  });
+
+  it("refunds only the tickets that were paid for", async () => {
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    const before = await getAccount(provider.connection, recipientTokenAccount);
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: (await batchesOf(program, rafflePda, recipient.publicKey))[0],
+        escrow: escrowPda,
+        buyerTokenAccount: recipientTokenAccount,
+        tokenMint,
+        refundRecipient: recipient.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const after = await getAccount(provider.connection, recipientTokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * MAX_PER_WALLET);
+  });
+});
+
+describe("rafflebot - gifted tickets", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let gifter: anchor.web3.Keypair;
+  let gifterTokenAccount: anchor.web3.PublicKey;
+  // Each gift records its own refund recipient: the gifter or the beneficiary
+  let beneficiaries: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const RAFFLE_NAME = "gift-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function gift(index: number, n: number, refundToPayer: boolean) {
+    const beneficiary = beneficiaries[index].kp.publicKey;
+    const [entryPda] = findEntryPda(program, rafflePda, beneficiary);
+    await program.methods
+      .buyTickets(n, ANY_COST, null, refundToPayer)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: gifterTokenAccount,
+        tokenMint,
+        buyer: gifter.publicKey,
+        beneficiary,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([gifter])
+      .rpc();
+  }
+
+  function refund(
+    batchIndex: number,
+    refundRecipient: anchor.web3.PublicKey,
+    tokenAccount: anchor.web3.PublicKey
+  ) {
+    return program.methods
+      .claimRefund()
+      .accounts({
+        raffle: rafflePda,
+        batch: findBatchPda(program, rafflePda, batchIndex)[0],
+        escrow: escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        refundRecipient,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    gifter = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(gifter.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    await sleep(1000);
+    gifterTokenAccount = await createAccount(provider.connection, payer, tokenMint, gifter.publicKey);
+    await mintTo(
+      provider.connection,
+      payer,
+      tokenMint,
+      gifterTokenAccount,
+      payer.publicKey,
+      100_000_000
+    );
+    for (let i = 0; i < 2; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      const tokenAccount = await createAccount(provider.connection, payer, tokenMint, kp.publicKey);
+      beneficiaries.push({ kp, tokenAccount });
+    }
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE * 100),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("gives the beneficiary the entry and tickets", async () => {
+    await gift(0, 2, true);
+    await gift(1, 3, false);
+
+    const [entryPda] = findEntryPda(program, rafflePda, beneficiaries[0].kp.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.buyer.toBase58()).to.equal(beneficiaries[0].kp.publicKey.toBase58());
+    expect(entry.numTickets).to.equal(2);
+
+    const batch = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 0)[0]);
+    expect(batch.refundRecipient.toBase58()).to.equal(gifter.publicKey.toBase58());
+
+    const { buyer } = await findWinningBatch(program, rafflePda, 0);
+    expect(buyer.toBase58()).to.equal(beneficiaries[0].kp.publicKey.toBase58());
+  });
+
+  it("records a different refund recipient for a later purchase", async () => {
+    // A purchase for the same beneficiary can't redirect earlier refunds
+    await gift(0, 1, false);
+
+    const batch = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 2)[0]);
+    expect(batch.refundRecipient.toBase58()).to.equal(
+      beneficiaries[0].kp.publicKey.toBase58()
+    );
+    const first = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 0)[0]);
+    expect(first.refundRecipient.toBase58()).to.equal(gifter.publicKey.toBase58());
+  });
+
+  it("refunds to the recipient recorded at purchase", async () => {
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    try {
+      await refund(1, gifter.publicKey, gifterTokenAccount);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RefundRecipientMismatch");
+    }
+
+    let before = await getAccount(provider.connection, gifterTokenAccount);
+    await refund(0, gifter.publicKey, gifterTokenAccount);
+    let after = await getAccount(provider.connection, gifterTokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * 2);
+
+    let { kp, tokenAccount } = beneficiaries[0];
+    before = await getAccount(provider.connection, tokenAccount);
+    await refund(2, kp.publicKey, tokenAccount);
+    after = await getAccount(provider.connection, tokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE);
+
+    ({ kp, tokenAccount } = beneficiaries[1]);
+    before = await getAccount(provider.connection, tokenAccount);
+    await refund(1, kp.publicKey, tokenAccount);
+    after = await getAccount(provider.connection, tokenAccount);
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * 3);
+  });
+});
+
+describe("rafflebot - ticket transfers", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let wallets: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const MAX_PER_WALLET = 3;
+  const RAFFLE_NAME = "transfer-test";
+
+  let rafflePda: anchor.web3.PublicKey;
+  let escrowPda: anchor.web3.PublicKey;
+
+  async function buy(index: number, n: number) {
+    const { kp, tokenAccount } = wallets[index];
+    const [entryPda] = findEntryPda(program, rafflePda, kp.publicKey);
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: entryPda,
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  async function transferTickets(from: number, to: number, batchIndex: number, n: number) {
+    const sender = wallets[from].kp;
+    const receiver = wallets[to].kp.publicKey;
+    await program.methods
+      .transferTickets(n)
+      .accounts({
+        raffle: rafflePda,
+        fromEntry: findEntryPda(program, rafflePda, sender.publicKey)[0],
+        toEntry: findEntryPda(program, rafflePda, receiver)[0],
+        batch: findBatchPda(program, rafflePda, batchIndex)[0],
+        newBatch: await nextBatchPda(program, rafflePda),
+        receiver,
+        sender: sender.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([sender])
+      .rpc();
+  }
+
+  async function ticketsOf(index: number) {
+    const [entryPda] = findEntryPda(program, rafflePda, wallets[index].kp.publicKey);
+    return (await program.account.entry.fetch(entryPda)).numTickets;
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 3; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      wallets.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const wallet of wallets) {
+      wallet.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    [rafflePda] = findRafflePda(program, payer.publicKey, RAFFLE_NAME);
+    [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        RAFFLE_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        MAX_PER_WALLET,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("moves part of a batch to another wallet", async () => {
+    await buy(0, 3);
+    await transferTickets(0, 1, 0, 2);
+
+    expect(await ticketsOf(0)).to.equal(1);
+    expect(await ticketsOf(1)).to.equal(2);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalTickets).to.equal(3);
+    expect(raffle.numBatches).to.equal(2);
+  });
+
+  it("keeps every ticket mapped to its current owner", async () => {
+    const sender = wallets[0].kp.publicKey.toBase58();
+    const receiver = wallets[1].kp.publicKey.toBase58();
+    const owners = [];
+    for (let ticket = 0; ticket < 3; ticket++) {
+      owners.push((await findWinningBatch(program, rafflePda, ticket)).buyer.toBase58());
+    }
+    expect(owners).to.deep.equal([sender, receiver, receiver]);
+  });
+
+  it("respects max_per_wallet on the receiving side", async () => {
+    await buy(2, 2);
+    try {
+      await transferTickets(2, 1, 2, 2);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MaxTicketsExceeded");
+    }
+    await transferTickets(2, 1, 2, 1);
+    expect(await ticketsOf(1)).to.equal(MAX_PER_WALLET);
+  });
+
+  it("rejects moving more tickets than the batch holds", async () => {
+    try {
+      await transferTickets(2, 0, 2, 2);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidTicketCount");
+    }
+  });
+
+  it("rejects a batch the sender does not own", async () => {
+    try {
+      await transferTickets(2, 0, 1, 1);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidTicketBatch");
+    }
+  });
+
+  it("keeps granted tickets granted when they move", async () => {
+    const sender = wallets[0].kp.publicKey;
+    const receiver = wallets[1].kp.publicKey;
+    const { numBatches } = await program.account.raffle.fetch(rafflePda);
+    await program.methods
+      .grantTickets(2)
+      .accounts({
+        raffle: rafflePda,
+        entry: findEntryPda(program, rafflePda, sender)[0],
+        batch: findBatchPda(program, rafflePda, numBatches)[0],
+        recipient: sender,
+        authority: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // The receiver is at the cap, but granted tickets don't count against it
+    await transferTickets(0, 1, numBatches, 2);
+
+    const from = await program.account.entry.fetch(findEntryPda(program, rafflePda, sender)[0]);
+    expect(from.numTickets).to.equal(1);
+    expect(from.grantedTickets).to.equal(0);
+    const to = await program.account.entry.fetch(findEntryPda(program, rafflePda, receiver)[0]);
+    expect(to.numTickets).to.equal(MAX_PER_WALLET + 2);
+    expect(to.grantedTickets).to.equal(2);
+
+    // The sender's bought ticket still counts against their cap
+    try {
+      await buy(0, MAX_PER_WALLET);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MaxTicketsExceeded");
+    }
+  });
+});
+
+describe("rafflebot - ticket tokens", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let wallets: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+
+  type TicketRaffle = {
+    rafflePda: anchor.web3.PublicKey;
+    escrowPda: anchor.web3.PublicKey;
+    ticketMint: anchor.web3.PublicKey;
+    ticketAccounts: anchor.web3.PublicKey[];
+  };
+  let drawn: TicketRaffle;
+  let refunded: TicketRaffle;
+  let endTime: number;
+
+  async function createTicketRaffle(
+    name: string,
+    end: number,
+    options = raffleOptions()
+  ): Promise<TicketRaffle> {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    const [ticketMint] = findTicketMintPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        0,
+        new anchor.BN(end),
+        options
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        ticketMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const ticketAccounts = [];
+    for (const { kp } of wallets) {
+      ticketAccounts.push(await createAccount(provider.connection, payer, ticketMint, kp.publicKey));
+    }
+    return { rafflePda, escrowPda, ticketMint, ticketAccounts };
+  }
+
+  async function buy(raffle: TicketRaffle, index: number, n: number) {
+    const { kp, tokenAccount } = wallets[index];
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle: raffle.rafflePda,
+        entry: findEntryPda(program, raffle.rafflePda, kp.publicKey)[0],
+        batch: null,
+        escrow: raffle.escrowPda,
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        ticketMint: raffle.ticketMint,
+        beneficiaryTicketAccount: raffle.ticketAccounts[index],
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  async function register(raffle: TicketRaffle, index: number) {
+    const { kp } = wallets[index];
+    await program.methods
+      .registerTickets()
+      .accounts({
+        raffle: raffle.rafflePda,
+        entry: findEntryPda(program, raffle.rafflePda, kp.publicKey)[0],
+        batch: await nextBatchPda(program, raffle.rafflePda),
+        ticketMint: raffle.ticketMint,
+        holderTicketAccount: raffle.ticketAccounts[index],
+        holder: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  async function ticketBalance(raffle: TicketRaffle, index: number) {
+    return Number((await getAccount(provider.connection, raffle.ticketAccounts[index])).amount);
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 3; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      wallets.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const wallet of wallets) {
+      wallet.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    endTime = Math.floor(Date.now() / 1000) + 15;
+    drawn = await createTicketRaffle("ticket-token-test", endTime);
+    refunded = await createTicketRaffle(
+      "ticket-token-refund",
+      Math.floor(Date.now() / 1000) + 3600
+    );
+  });
+
+  it("mints one ticket token per ticket bought", async () => {
+    await buy(drawn, 0, 3);
+    await buy(drawn, 1, 2);
+
+    expect(await ticketBalance(drawn, 0)).to.equal(3);
+    expect(await ticketBalance(drawn, 1)).to.equal(2);
+    const raffle = await program.account.raffle.fetch(drawn.rafflePda);
+    expect(raffle.totalTickets).to.equal(5);
+    expect(raffle.numBatches).to.equal(0);
+  });
+
+  it("lets ticket tokens be traded like any SPL token", async () => {
+    await transfer(
+      provider.connection,
+      payer,
+      drawn.ticketAccounts[0],
+      drawn.ticketAccounts[2],
+      wallets[0].kp,
+      1
+    );
+    expect(await ticketBalance(drawn, 2)).to.equal(1);
+  });
+
+  it("rejects registration while sales are open", async () => {
+    try {
+      await register(drawn, 0);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleNotEnded");
+    }
+  });
+
+  it("snapshots holders into ticket ranges after end_time", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    if (now < endTime) {
+      await sleep((endTime - now + 2) * 1000);
+    }
+
+    for (const index of [0, 1, 2]) {
+      await register(drawn, index);
+      expect(await ticketBalance(drawn, index)).to.equal(0);
+    }
+
+    const raffle = await program.account.raffle.fetch(drawn.rafflePda);
+    expect(raffle.registeredTickets).to.equal(5);
+    const owners = [];
+    for (let ticket = 0; ticket < 5; ticket++) {
+      owners.push((await findWinningBatch(program, drawn.rafflePda, ticket)).buyer.toBase58());
+    }
+    const [a, b, c] = wallets.map(({ kp }) => kp.publicKey.toBase58());
+    expect(owners).to.deep.equal([a, a, b, b, c]);
+  });
+
+  it("draws without unregistered tokens once the creator's window closes", async () => {
+    const WINDOW = 5;
+    const end = Math.floor(Date.now() / 1000) + 8;
+    const raffle = await createTicketRaffle(
+      "ticket-token-window",
+      end,
+      raffleOptions({ registrationWindow: new anchor.BN(WINDOW) })
+    );
+    await buy(raffle, 0, 1);
+    await buy(raffle, 1, 1);
+
+    await sleep((end - Math.floor(Date.now() / 1000) + 2) * 1000);
+    await register(raffle, 0);
+    await sleep((WINDOW + 2) * 1000);
+    await commitVrfDraw(program, provider, payer, raffle.rafflePda);
+
+    const committed = await program.account.raffle.fetch(raffle.rafflePda);
+    expect(committed.status).to.deep.equal({ drawCommitted: {} });
+    expect(committed.registeredTickets).to.equal(1);
+    expect(committed.totalPot.toNumber()).to.equal(2 * TICKET_PRICE);
+  });
+
+  it("rejects a registration window that isn't positive", async () => {
+    try {
+      await createTicketRaffle(
+        "ticket-token-bad-window",
+        Math.floor(Date.now() / 1000) + 3600,
+        raffleOptions({ registrationWindow: new anchor.BN(0) })
+      );
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidRegistrationWindow");
+    }
+  });
+
+  it("burns ticket tokens for a refund once cancelled", async () => {
+    await buy(refunded, 0, 2);
+    await buy(refunded, 1, 1);
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: refunded.rafflePda, authority: payer.publicKey })
+      .rpc();
+
+    const { kp, tokenAccount } = wallets[0];
+    const before = await getAccount(provider.connection, tokenAccount);
+    await program.methods
+      .refundTicketTokens()
+      .accounts({
+        raffle: refunded.rafflePda,
+        escrow: refunded.escrowPda,
+        holderTokenAccount: tokenAccount,
+        tokenMint,
+        ticketMint: refunded.ticketMint,
+        holderTicketAccount: refunded.ticketAccounts[0],
+        holder: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([kp])
+      .rpc();
+    const after = await getAccount(provider.connection, tokenAccount);
+
+    expect(Number(after.amount) - Number(before.amount)).to.equal(TICKET_PRICE * 2);
+    expect(await ticketBalance(refunded, 0)).to.equal(0);
+  });
+
+  it("keeps the raffle open until every ticket token is refunded", async () => {
+    const close = () =>
+      program.methods
+        .closeRaffle()
+        .accounts({
+          raffle: refunded.rafflePda,
+          escrow: refunded.escrowPda,
+          platformTokenAccount: null,
+          treasury: null,
+          config: findConfigPda(program)[0],
+          tokenMint,
+          ticketMint: refunded.ticketMint,
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    for (const index of [0, 1]) {
+      const { kp } = wallets[index];
+      await program.methods
+        .closeEntry()
+        .accounts({
+          raffle: refunded.rafflePda,
+          entry: findEntryPda(program, refunded.rafflePda, kp.publicKey)[0],
+          buyer: kp.publicKey,
+        })
+        .rpc();
+    }
+
+    try {
+      await close();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("TicketRefundsOutstanding");
+    }
+
+    const { kp, tokenAccount } = wallets[1];
+    await program.methods
+      .refundTicketTokens()
+      .accounts({
+        raffle: refunded.rafflePda,
+        escrow: refunded.escrowPda,
+        holderTokenAccount: tokenAccount,
+        tokenMint,
+        ticketMint: refunded.ticketMint,
+        holderTicketAccount: refunded.ticketAccounts[1],
+        holder: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([kp])
+      .rpc();
+
+    await close();
+    expect(await provider.connection.getAccountInfo(refunded.rafflePda)).to.equal(null);
+  });
+});
+
+describe("rafflebot - ticket withdrawal", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let wallets: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const PENALTY_BPS = 1_000;
+  const RAFFLE_NAME = "withdraw-test";
+  const LOCKED_RAFFLE_NAME = "withdraw-locked";
+
+  async function createRaffle(name: string, withdrawPenaltyBps: number | null) {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        10,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions({ withdrawPenaltyBps })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return rafflePda;
+  }
+
+  async function buy(rafflePda: anchor.web3.PublicKey, index: number, n: number) {
+    const { kp, tokenAccount } = wallets[index];
+    await program.methods
+      .buyTickets(n, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: findEntryPda(program, rafflePda, kp.publicKey)[0],
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: findEscrowPda(program, rafflePda)[0],
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  async function withdraw(
+    rafflePda: anchor.web3.PublicKey,
+    index: number,
+    batchIndex: number,
+    n: number
+  ) {
+    const { kp, tokenAccount } = wallets[index];
+    await program.methods
+      .withdrawTickets(n)
+      .accounts({
+        raffle: rafflePda,
+        entry: findEntryPda(program, rafflePda, kp.publicKey)[0],
+        batch: findBatchPda(program, rafflePda, batchIndex)[0],
+        escrow: findEscrowPda(program, rafflePda)[0],
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        refundRecipient: kp.publicKey,
+        buyer: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([kp])
+      .rpc();
+  }
+
+  let rafflePda: anchor.web3.PublicKey;
+  let lockedRafflePda: anchor.web3.PublicKey;
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 3; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      wallets.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const wallet of wallets) {
+      wallet.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    rafflePda = await createRaffle(RAFFLE_NAME, PENALTY_BPS);
+    lockedRafflePda = await createRaffle(LOCKED_RAFFLE_NAME, null);
+  });
+
+  it("refunds withdrawn tickets minus the penalty", async () => {
+    await buy(rafflePda, 0, 3);
+    await buy(rafflePda, 1, 2);
+
+    const before = await getAccount(provider.connection, wallets[0].tokenAccount);
+    await withdraw(rafflePda, 0, 0, 2);
+    const after = await getAccount(provider.connection, wallets[0].tokenAccount);
+
+    const refund = (2 * TICKET_PRICE * (10_000 - PENALTY_BPS)) / 10_000;
+    expect(Number(after.amount - before.amount)).to.equal(refund);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalTickets).to.equal(3);
+    expect(raffle.totalPot.toNumber()).to.equal(5 * TICKET_PRICE - refund);
+    expect(raffle.withdrawn).to.have.length(1);
+    expect(raffle.withdrawn[0].start).to.equal(1);
+    expect(raffle.withdrawn[0].numTickets).to.equal(2);
+
+    const [entryPda] = findEntryPda(program, rafflePda, wallets[0].kp.publicKey);
+    const entry = await program.account.entry.fetch(entryPda);
+    expect(entry.numTickets).to.equal(1);
+    const [batchPda] = await batchesOf(program, rafflePda, wallets[0].kp.publicKey);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    expect(batch.amountPaid.toNumber()).to.equal(TICKET_PRICE);
+  });
+
+  it("keeps other buyers' ticket indices", async () => {
+    const batch = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 1)[0]);
+    expect(batch.startTicketIndex).to.equal(3);
+    const { buyer } = await findWinningBatch(program, rafflePda, 4);
+    expect(buyer.toBase58()).to.equal(wallets[1].kp.publicKey.toBase58());
+
+    await buy(rafflePda, 2, 1);
+    const newBatch = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 2)[0]);
+    expect(newBatch.startTicketIndex).to.equal(5);
+  });
+
+  it("rejects withdrawing more tickets than the batch holds", async () => {
+    try {
+      await withdraw(rafflePda, 1, 1, 3);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidTicketCount");
+    }
+  });
+
+  it("rejects withdrawals on raffles without a penalty", async () => {
+    await buy(lockedRafflePda, 0, 1);
+    try {
+      await withdraw(lockedRafflePda, 0, 0, 1);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("WithdrawalsDisabled");
+    }
+  });
+
+  it("rejects a penalty above 100%", async () => {
+    try {
+      await createRaffle("withdraw-bad", 10_001);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidWithdrawPenalty");
+    }
+  });
+
+  it("rejects a penalty below the minimum", async () => {
+    try {
+      await createRaffle("withdraw-free", 0);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidWithdrawPenalty");
+    }
+  });
+
+  it("values withdrawn tickets at what their batch paid after a transfer", async () => {
+    await buy(rafflePda, 2, 4);
+    const sender = wallets[2].kp;
+    const receiver = wallets[1].kp.publicKey;
+    await program.methods
+      .transferTickets(3)
+      .accounts({
+        raffle: rafflePda,
+        fromEntry: findEntryPda(program, rafflePda, sender.publicKey)[0],
+        toEntry: findEntryPda(program, rafflePda, receiver)[0],
+        batch: findBatchPda(program, rafflePda, 3)[0],
+        newBatch: await nextBatchPda(program, rafflePda),
+        receiver,
+        sender: sender.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([sender])
+      .rpc();
+
+    const potBefore = (await program.account.raffle.fetch(rafflePda)).totalPot.toNumber();
+    const before = await getAccount(provider.connection, wallets[2].tokenAccount);
+    await withdraw(rafflePda, 2, 3, 1);
+    const after = await getAccount(provider.connection, wallets[2].tokenAccount);
+
+    const refund = (TICKET_PRICE * (10_000 - PENALTY_BPS)) / 10_000;
+    expect(Number(after.amount - before.amount)).to.equal(refund);
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalPot.toNumber()).to.equal(potBefore - refund);
+
+    const moved = await program.account.ticketBatch.fetch(findBatchPda(program, rafflePda, 4)[0]);
+    expect(moved.numTickets).to.equal(3);
+    expect(moved.amountPaid.toNumber()).to.equal(3 * TICKET_PRICE);
+  });
+});
+
+describe("rafflebot - raffle series", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let buyer: anchor.web3.Keypair;
+  let buyerTokenAccount: anchor.web3.PublicKey;
+
+  const TICKET_PRICE = 1_000_000;
+  const DURATION = 7 * 86_400;
+  const SERIES_NAME = "weekly";
+
+  let seriesPda: anchor.web3.PublicKey;
+
+  async function openRound(round: number, previousRaffle: anchor.web3.PublicKey) {
+    const [rafflePda] = findRoundPda(program, seriesPda, round);
+    await program.methods
+      .openRound()
+      .accounts({
+        series: seriesPda,
+        raffle: rafflePda,
+        previousRaffle,
+        escrow: findEscrowPda(program, rafflePda)[0],
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return rafflePda;
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    buyer = anchor.web3.Keypair.generate();
+    await provider.connection.requestAirdrop(buyer.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    await sleep(1000);
+    buyerTokenAccount = await createAccount(provider.connection, payer, tokenMint, buyer.publicKey);
+    await mintTo(provider.connection, payer, tokenMint, buyerTokenAccount, payer.publicKey, 100_000_000);
+
+    [seriesPda] = findSeriesPda(program, payer.publicKey, SERIES_NAME);
+  });
+
+  it("creates a series from a template", async () => {
+    await program.methods
+      .createSeries(
+        SERIES_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(5 * TICKET_PRICE),
+        10,
+        new anchor.BN(DURATION),
+        raffleOptions()
+      )
+      .accounts({
+        series: seriesPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const series = await program.account.series.fetch(seriesPda);
+    expect(series.round).to.equal(0);
+    expect(series.currentRaffle).to.be.null;
+    expect(series.duration.toNumber()).to.equal(DURATION);
+  });
+
+  it("opens the first round at the series + round PDA", async () => {
+    const rafflePda = await openRound(1, anchor.web3.SystemProgram.programId);
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.name).to.equal(SERIES_NAME);
+    expect(raffle.series.toBase58()).to.equal(seriesPda.toBase58());
+    expect(raffle.round).to.equal(1);
+    expect(raffle.ticketPrice.toNumber()).to.equal(TICKET_PRICE);
+    expect(raffle.endTime.sub(raffle.startTime).toNumber()).to.equal(DURATION);
+
+    const series = await program.account.series.fetch(seriesPda);
+    expect(series.round).to.equal(1);
+    expect(series.currentRaffle.toBase58()).to.equal(rafflePda.toBase58());
+  });
+
+  it("sells tickets in a round", async () => {
+    const [rafflePda] = findRoundPda(program, seriesPda, 1);
+    await program.methods
+      .buyTickets(2, ANY_COST, null, false)
+      .accounts({
+        raffle: rafflePda,
+        entry: findEntryPda(program, rafflePda, buyer.publicKey)[0],
+        batch: await nextBatchPda(program, rafflePda),
+        escrow: findEscrowPda(program, rafflePda)[0],
+        buyerTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        beneficiary: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.totalTickets).to.equal(2);
+  });
+
+  it("rejects opening a round while the current one is open", async () => {
+    try {
+      await openRound(2, findRoundPda(program, seriesPda, 1)[0]);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RoundStillOpen");
+    }
+  });
+
+  it("rejects a previous round that isn't the current one", async () => {
+    try {
+      await openRound(2, anchor.web3.Keypair.generate().publicKey);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidPreviousRound");
+    }
+  });
+
+  it("opens the next round once the current one is finished", async () => {
+    const [round1] = findRoundPda(program, seriesPda, 1);
+    await program.methods
+      .cancelRaffle()
+      .accounts({ raffle: round1, authority: payer.publicKey })
+      .rpc();
+
+    const round2 = await openRound(2, round1);
+    const raffle = await program.account.raffle.fetch(round2);
+    expect(raffle.round).to.equal(2);
+    expect(raffle.totalTickets).to.equal(0);
+
+    const series = await program.account.series.fetch(seriesPda);
+    expect(series.round).to.equal(2);
+  });
+
+  it("rejects a template with a start time", async () => {
+    try {
+      await program.methods
+        .createSeries(
+          "bad-series",
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          10,
+          new anchor.BN(DURATION),
+          raffleOptions({ startTime: new anchor.BN(Math.floor(Date.now() / 1000) + 60) })
+        )
+        .accounts({
+          series: findSeriesPda(program, payer.publicKey, "bad-series")[0],
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidStartTime");
+    }
+  });
+});
+
+describe("rafflebot - pot rollover", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let tokenMint: anchor.web3.PublicKey;
+  let wallets: { kp: anchor.web3.Keypair; tokenAccount: anchor.web3.PublicKey }[] = [];
+
+  const TICKET_PRICE = 1_000_000;
+  const DURATION = 10;
+  const GRACE = 4;
+  const MAX_PER_WALLET = 10;
+  const MAX_TICKETS = 13;
+  const PENALTY_BPS = 1_000;
+  const SERIES_NAME = "rollover";
+
+  let grantee: anchor.web3.Keypair;
+  let seriesPda: anchor.web3.PublicKey;
+  let round1: anchor.web3.PublicKey;
+  let round2: anchor.web3.PublicKey;
+
+  async function openRound(round: number, previousRaffle: anchor.web3.PublicKey) {
+    const [rafflePda] = findRoundPda(program, seriesPda, round);
+    await program.methods
+      .openRound()
+      .accounts({
+        series: seriesPda,
+        raffle: rafflePda,
+        previousRaffle,
+        escrow: findEscrowPda(program, rafflePda)[0],
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return rafflePda;
+  }
+
+  function claimRefund(index: number, batch: anchor.web3.PublicKey, signer = wallets[index].kp) {
+    const { kp, tokenAccount } = wallets[index];
+    return program.methods
+      .claimRefund()
+      .accounts({
+        raffle: round1,
+        batch,
+        escrow: findEscrowPda(program, round1)[0],
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        refundRecipient: kp.publicKey,
+        nextRaffle: round2,
+        owner: signer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([signer])
+      .rpc();
+  }
+
+  async function rollBatch(index: number) {
+    const buyer = wallets[index].kp.publicKey;
+    await program.methods
+      .rollBatch(null)
+      .accounts({
+        raffle: round1,
+        batch: (await batchesOf(program, round1, buyer))[0],
+        escrow: findEscrowPda(program, round1)[0],
+        nextRaffle: round2,
+        nextEntry: findEntryPda(program, round2, buyer)[0],
+        nextBatch: await nextBatchPda(program, round2),
+        nextEscrow: findEscrowPda(program, round2)[0],
+        tokenMint,
+        payer: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  before(async () => {
+    tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    await ensureConfig(program, payer);
+
+    for (let i = 0; i < 4; i++) {
+      const kp = anchor.web3.Keypair.generate();
+      await provider.connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      wallets.push({ kp, tokenAccount: anchor.web3.PublicKey.default });
+    }
+    await sleep(1000);
+    for (const wallet of wallets) {
+      wallet.tokenAccount = await createAccount(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.kp.publicKey
+      );
+      await mintTo(
+        provider.connection,
+        payer,
+        tokenMint,
+        wallet.tokenAccount,
+        payer.publicKey,
+        100_000_000
+      );
+    }
+
+    [seriesPda] = findSeriesPda(program, payer.publicKey, SERIES_NAME);
+    await program.methods
+      .createSeries(
+        SERIES_NAME,
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(10 * TICKET_PRICE),
+        MAX_PER_WALLET,
+        new anchor.BN(DURATION),
+        raffleOptions({
+          rolloverGrace: new anchor.BN(GRACE),
+          maxTickets: MAX_TICKETS,
+          withdrawPenaltyBps: PENALTY_BPS,
+        })
+      )
+      .accounts({
+        series: seriesPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    round1 = await openRound(1, anchor.web3.SystemProgram.programId);
+    [round2] = findRoundPda(program, seriesPda, 2);
+
+    for (let i = 0; i < wallets.length; i++) {
+      const { kp, tokenAccount } = wallets[i];
+      await program.methods
+        .buyTickets(2, ANY_COST, null, false)
+        .accounts({
+          raffle: round1,
+          entry: findEntryPda(program, round1, kp.publicKey)[0],
+          batch: await nextBatchPda(program, round1),
+          escrow: findEscrowPda(program, round1)[0],
+          buyerTokenAccount: tokenAccount,
+          tokenMint,
+          buyer: kp.publicKey,
+          beneficiary: kp.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+    }
+
+    // The penalty on this withdrawal stays in the pot
+    const { kp, tokenAccount } = wallets[1];
+    await program.methods
+      .withdrawTickets(1)
+      .accounts({
+        raffle: round1,
+        entry: findEntryPda(program, round1, kp.publicKey)[0],
+        batch: (await batchesOf(program, round1, kp.publicKey))[0],
+        escrow: findEscrowPda(program, round1)[0],
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        refundRecipient: kp.publicKey,
+        buyer: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([kp])
+      .rpc();
+
+    grantee = anchor.web3.Keypair.generate();
+    await program.methods
+      .grantTickets(1)
+      .accounts({
+        raffle: round1,
+        entry: findEntryPda(program, round1, grantee.publicKey)[0],
+        batch: await nextBatchPda(program, round1),
+        recipient: grantee.publicKey,
+        authority: payer.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("rejects rollover for a series with deposited prize assets", async () => {
+    try {
+      await program.methods
+        .createSeries(
+          "rollover-assets",
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(10 * TICKET_PRICE),
+          10,
+          new anchor.BN(DURATION),
+          raffleOptions({ rolloverGrace: new anchor.BN(GRACE), prizeMode: { assets: {} } })
+        )
+        .accounts({
+          series: findSeriesPda(program, payer.publicKey, "rollover-assets")[0],
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidRollover");
+    }
+  });
+
+  it("rejects rolling over before end_time", async () => {
+    try {
+      await program.methods.rolloverRaffle().accounts({ raffle: round1 }).rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleNotEnded");
+    }
+  });
+
+  it("rolls an unmet pot over to the next round", async () => {
+    await sleep((DURATION + 1) * 1000);
+    await program.methods.rolloverRaffle().accounts({ raffle: round1 }).rpc();
+
+    const raffle = await program.account.raffle.fetch(round1);
+    expect(raffle.status).to.deep.equal({ rolledOver: {} });
+    expect(raffle.rolloverTo.toBase58()).to.equal(round2.toBase58());
+
+    await openRound(2, round1);
+  });
+
+  it("keeps granted batches open until they are carried over", async () => {
+    try {
+      await program.methods
+        .closeEntry()
+        .accounts({
+          raffle: round1,
+          entry: findEntryPda(program, round1, grantee.publicKey)[0],
+          buyer: grantee.publicKey,
+        })
+        .remainingAccounts(
+          (await batchesOf(program, round1, grantee.publicKey)).map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleNotFinished");
+    }
+  });
+
+  it("only lets the batch owner opt out", async () => {
+    const { kp } = wallets[1];
+    try {
+      await claimRefund(1, (await batchesOf(program, round1, kp.publicKey))[0], payer);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("NotEntryOwner");
+    }
+  });
+
+  it("refunds buyers who opt out during the grace window", async () => {
+    const { kp, tokenAccount } = wallets[1];
+    const before = await getAccount(provider.connection, tokenAccount);
+    await claimRefund(1, (await batchesOf(program, round1, kp.publicKey))[0]);
+    const after = await getAccount(provider.connection, tokenAccount);
+    expect(Number(after.amount - before.amount)).to.equal(TICKET_PRICE);
+  });
+
+  it("rejects carrying batches over during the grace window", async () => {
+    try {
+      await rollBatch(0);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RolloverGraceActive");
+    }
+  });
+
+  it("carries remaining batches into the next round", async () => {
+    await sleep((GRACE + 1) * 1000);
+    await rollBatch(0);
+
+    const raffle = await program.account.raffle.fetch(round2);
+    expect(raffle.totalTickets).to.equal(2);
+    expect(raffle.totalPot.toNumber()).to.equal(2 * TICKET_PRICE);
+
+    const buyer = wallets[0].kp.publicKey;
+    const entry = await program.account.entry.fetch(findEntryPda(program, round2, buyer)[0]);
+    expect(entry.numTickets).to.equal(2);
+    const [batchPda] = await batchesOf(program, round2, buyer);
+    const batch = await program.account.ticketBatch.fetch(batchPda);
+    expect(batch.amountPaid.toNumber()).to.equal(2 * TICKET_PRICE);
+    expect(batch.refundRecipient.toBase58()).to.equal(buyer.toBase58());
+
+    const [oldBatchPda] = await batchesOf(program, round1, buyer);
+    const oldBatch = await program.account.ticketBatch.fetch(oldBatchPda);
+    expect(oldBatch.refunded).to.be.true;
+
+    // The last two wallets' batches are still waiting to be carried over,
+    // next to the withdraw penalty
+    const penalty = (TICKET_PRICE * PENALTY_BPS) / 10_000;
+    const escrow = await getAccount(provider.connection, findEscrowPda(program, round1)[0]);
+    expect(Number(escrow.amount)).to.equal(4 * TICKET_PRICE + penalty);
+  });
+
+  it("carries batches past the next round's wallet cap", async () => {
+    const { kp, tokenAccount } = wallets[2];
+    await program.methods
+      .buyTickets(MAX_PER_WALLET - 1, ANY_COST, null, false)
+      .accounts({
+        raffle: round2,
+        entry: findEntryPda(program, round2, kp.publicKey)[0],
+        batch: await nextBatchPda(program, round2),
+        escrow: findEscrowPda(program, round2)[0],
+        buyerTokenAccount: tokenAccount,
+        tokenMint,
+        buyer: kp.publicKey,
+        beneficiary: kp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([kp])
+      .rpc();
+
+    // Carried tickets were bought in the earlier round
+    await rollBatch(2);
+    const entry = await program.account.entry.fetch(findEntryPda(program, round2, kp.publicKey)[0]);
+    expect(entry.numTickets).to.equal(MAX_PER_WALLET + 1);
+  });
+
+  it("rejects carrying a batch over twice", async () => {
+    try {
+      await rollBatch(0);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("AlreadyRefunded");
+    }
+  });
+
+  it("rejects opting out after the grace window while the next round is open", async () => {
+    const { kp } = wallets[3];
+    try {
+      await claimRefund(3, (await batchesOf(program, round1, kp.publicKey))[0]);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RolloverGraceEnded");
+    }
+  });
+
+  it("refunds batches the next round has no room for", async () => {
+    // The next round is now full
+    const round = await program.account.raffle.fetch(round2);
+    expect(round.totalTickets).to.equal(MAX_TICKETS);
+
+    const { kp, tokenAccount } = wallets[3];
+    const [batchPda] = await batchesOf(program, round1, kp.publicKey);
+    try {
+      await rollBatch(3);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleSoldOut");
+    }
+
+    const before = await getAccount(provider.connection, tokenAccount);
+    await claimRefund(3, batchPda);
+    const after = await getAccount(provider.connection, tokenAccount);
+    expect(Number(after.amount - before.amount)).to.equal(2 * TICKET_PRICE);
+
+    // Granted batches opt out the same way, for nothing
+    const [grantedPda] = await batchesOf(program, round1, grantee.publicKey);
+    await program.methods
+      .claimRefund()
+      .accounts({
+        raffle: round1,
+        batch: grantedPda,
+        escrow: findEscrowPda(program, round1)[0],
+        buyerTokenAccount: null,
+        tokenMint,
+        refundRecipient: grantee.publicKey,
+        nextRaffle: round2,
+        owner: grantee.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([grantee])
+      .rpc();
   });
 
-  it("cannot double-claim refund", async () => {
-    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
-
-    try {
+  it("carries the withdraw penalties into the next round on close", async () => {
+    const owners = [...wallets.map(({ kp }) => kp.publicKey), grantee.publicKey];
+    for (const owner of owners) {
       await program.methods
-        .claimRefund()
+        .closeEntry()
         .accounts({
-          raffle: rafflePda,
-          entry: entryPda,
-          escrow: escrowPda,
-          buyerTokenAccount,
-          tokenMint,
-          buyer: buyer.publicKey,
-          tokenProgram: TOKEN_PROGRAM_ID,
+          raffle: round1,
+          entry: findEntryPda(program, round1, owner)[0],
+          buyer: owner,
         })
+        .remainingAccounts(
+          (await batchesOf(program, round1, owner)).map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
         .rpc();
-      expect.fail("Should have thrown");
-    } catch (e: any) {
-      expect(e.message).to.include("AlreadyRefunded");
     }
+
+    const before = await program.account.raffle.fetch(round2);
+    await program.methods
+      .closeRaffle()
+      .accounts({
+        raffle: round1,
+        escrow: findEscrowPda(program, round1)[0],
+        platformTokenAccount: null,
+        treasury: null,
+        config: findConfigPda(program)[0],
+        tokenMint,
+        ticketMint: null,
+        nextRaffle: round2,
+        nextEscrow: findEscrowPda(program, round2)[0],
+        nextVault: null,
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const penalty = (TICKET_PRICE * PENALTY_BPS) / 10_000;
+    const after = await program.account.raffle.fetch(round2);
+    expect(after.totalPot.toNumber() - before.totalPot.toNumber()).to.equal(penalty);
+    expect(await provider.connection.getAccountInfo(round1)).to.equal(null);
   });
 });
 
@@ -519,7 +4975,6 @@ describe("rafflebot - validation errors", () => {
   const payer = (provider.wallet as anchor.Wallet).payer;
 
   let tokenMint: anchor.web3.PublicKey;
-  let platformWallet: anchor.web3.Keypair;
   let buyer: anchor.web3.Keypair;
   let buyerTokenAccount: anchor.web3.PublicKey;
 
@@ -528,7 +4983,7 @@ describe("rafflebot - validation errors", () => {
 
   before(async () => {
     tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
-    platformWallet = anchor.web3.Keypair.generate();
+    await ensureConfig(program, payer);
 
     buyer = anchor.web3.Keypair.generate();
     await provider.connection.requestAirdrop(
@@ -565,13 +5020,14 @@ describe("rafflebot - validation errors", () => {
           new anchor.BN(TICKET_PRICE),
           new anchor.BN(TICKET_PRICE),
           10,
-          new anchor.BN(pastTime)
+          new anchor.BN(pastTime),
+          raffleOptions()
         )
         .accounts({
           raffle: rafflePda,
           escrow: escrowPda,
           tokenMint,
-          platformWallet: platformWallet.publicKey,
+          config: findConfigPda(program)[0],
           authority: payer.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -583,6 +5039,89 @@ describe("rafflebot - validation errors", () => {
     }
   });
 
+  it("rejects a start time at or after the end time", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 3600;
+    const [rafflePda] = findRafflePda(program, payer.publicKey, "late-start");
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+
+    try {
+      await program.methods
+        .createRaffle(
+          "late-start",
+          new anchor.BN(TICKET_PRICE),
+          new anchor.BN(TICKET_PRICE),
+          10,
+          new anchor.BN(endTime),
+          raffleOptions({ startTime: new anchor.BN(endTime) })
+        )
+        .accounts({
+          raffle: rafflePda,
+          escrow: escrowPda,
+          tokenMint,
+          config: findConfigPda(program)[0],
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidStartTime");
+    }
+  });
+
+  it("rejects ticket purchases before the start time", async () => {
+    const startTime = Math.floor(Date.now() / 1000) + 1800;
+    const [rafflePda] = findRafflePda(program, payer.publicKey, "scheduled-test");
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+
+    await program.methods
+      .createRaffle(
+        "scheduled-test",
+        new anchor.BN(TICKET_PRICE),
+        new anchor.BN(TICKET_PRICE),
+        10,
+        new anchor.BN(startTime + 3600),
+        raffleOptions({ startTime: new anchor.BN(startTime) })
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: findConfigPda(program)[0],
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.startTime.toNumber()).to.equal(startTime);
+
+    const [entryPda] = findEntryPda(program, rafflePda, buyer.publicKey);
+    try {
+      await program.methods
+        .buyTickets(1, ANY_COST, null, false)
+        .accounts({
+          raffle: rafflePda,
+          entry: entryPda,
+          batch: await nextBatchPda(program, rafflePda),
+          escrow: escrowPda,
+          buyerTokenAccount,
+          tokenMint,
+          buyer: buyer.publicKey,
+          beneficiary: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("RaffleNotStarted");
+    }
+  });
+
   it("rejects zero ticket price", async () => {
     const endTime = Math.floor(Date.now() / 1000) + 3600;
     const [rafflePda] = findRafflePda(program, payer.publicKey, "zero-price");
@@ -590,12 +5129,12 @@ describe("rafflebot - validation errors", () => {
 
     try {
       await program.methods
-        .createRaffle("zero-price", new anchor.BN(0), new anchor.BN(1), 10, new anchor.BN(endTime))
+        .createRaffle("zero-price", new anchor.BN(0), new anchor.BN(1), 10, new anchor.BN(endTime), raffleOptions())
         .accounts({
           raffle: rafflePda,
           escrow: escrowPda,
           tokenMint,
-          platformWallet: platformWallet.publicKey,
+          config: findConfigPda(program)[0],
           authority: payer.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -618,13 +5157,14 @@ describe("rafflebot - validation errors", () => {
         new anchor.BN(TICKET_PRICE),
         new anchor.BN(TICKET_PRICE),
         2,
-        new anchor.BN(endTime)
+        new anchor.BN(endTime),
+        raffleOptions()
       )
       .accounts({
         raffle: rafflePda,
         escrow: escrowPda,
         tokenMint,
-        platformWallet: platformWallet.publicKey,
+        config: findConfigPda(program)[0],
         authority: payer.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -635,14 +5175,16 @@ describe("rafflebot - validation errors", () => {
 
     try {
       await program.methods
-        .buyTickets(3)
+        .buyTickets(3, ANY_COST, null, false)
         .accounts({
           raffle: rafflePda,
           entry: entryPda,
+          batch: await nextBatchPda(program, rafflePda),
           escrow: escrowPda,
           buyerTokenAccount,
           tokenMint,
           buyer: buyer.publicKey,
+          beneficiary: buyer.publicKey,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
         })
@@ -675,3 +5217,321 @@ describe("rafflebot - validation errors", () => {
     }
   });
 });
+
+describe("rafflebot - protocol config", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let configPda: anchor.web3.PublicKey;
+
+  before(async () => {
+    await ensureConfig(program, payer);
+    [configPda] = findConfigPda(program);
+  });
+
+  it("non-admin cannot update config", async () => {
+    const attacker = anchor.web3.Keypair.generate();
+
+    try {
+      await program.methods
+        .updateConfig(null, attacker.publicKey, null, null, null)
+        .accounts({ config: configPda, admin: attacker.publicKey })
+        .signers([attacker])
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("Unauthorized");
+    }
+  });
+
+  it("admin can change the fee, which is snapshotted by new raffles", async () => {
+    const original = await program.account.config.fetch(configPda);
+
+    await program.methods
+      .updateConfig(null, null, 500, null, null)
+      .accounts({ config: configPda, admin: payer.publicKey })
+      .rpc();
+
+    const tokenMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const [rafflePda] = findRafflePda(program, payer.publicKey, "config-fee-test");
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        "config-fee-test",
+        new anchor.BN(1_000_000),
+        new anchor.BN(1_000_000),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: configPda,
+        authority: payer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const raffle = await program.account.raffle.fetch(rafflePda);
+    expect(raffle.feeBps).to.equal(500);
+
+    await program.methods
+      .updateConfig(null, null, original.feeBps, null, null)
+      .accounts({ config: configPda, admin: payer.publicKey })
+      .rpc();
+  });
+
+  it("rejects fees above the maximum", async () => {
+    try {
+      await program.methods
+        .updateConfig(null, null, 5_000, null, null)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("InvalidFee");
+    }
+  });
+
+  it("enforces the mint allowlist once it is non-empty", async () => {
+    const allowedMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const otherMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+
+    await program.methods
+      .addAllowedMint(allowedMint)
+      .accounts({ config: configPda, admin: payer.publicKey })
+      .rpc();
+
+    const [rafflePda] = findRafflePda(program, payer.publicKey, "allowlist-test");
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    try {
+      await program.methods
+        .createRaffle(
+          "allowlist-test",
+          new anchor.BN(1_000_000),
+          new anchor.BN(1_000_000),
+          0,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          raffleOptions()
+        )
+        .accounts({
+          raffle: rafflePda,
+          escrow: escrowPda,
+          tokenMint: otherMint,
+          config: configPda,
+          authority: payer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintNotAllowed");
+    } finally {
+      await program.methods
+        .removeAllowedMint(allowedMint)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    }
+  });
+});
+
+describe("rafflebot - mint safety screening", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Rafflebot as Program<Rafflebot>;
+  const payer = (provider.wallet as anchor.Wallet).payer;
+
+  let configPda: anchor.web3.PublicKey;
+
+  before(async () => {
+    await ensureConfig(program, payer);
+    [configPda] = findConfigPda(program);
+  });
+
+  async function createRaffleWithMint(
+    name: string,
+    tokenMint: anchor.web3.PublicKey,
+    tokenProgram: anchor.web3.PublicKey
+  ) {
+    const [rafflePda] = findRafflePda(program, payer.publicKey, name);
+    const [escrowPda] = findEscrowPda(program, rafflePda);
+    await program.methods
+      .createRaffle(
+        name,
+        new anchor.BN(1_000_000),
+        new anchor.BN(1_000_000),
+        0,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        raffleOptions()
+      )
+      .accounts({
+        raffle: rafflePda,
+        escrow: escrowPda,
+        tokenMint,
+        config: configPda,
+        authority: payer.publicKey,
+        tokenProgram,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    return rafflePda;
+  }
+
+  it("rejects a mint with a freeze authority", async () => {
+    const tokenMint = await createMint(
+      provider.connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6
+    );
+
+    try {
+      await createRaffleWithMint("screen-freeze", tokenMint, TOKEN_PROGRAM_ID);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintHasFreezeAuthority");
+    }
+  });
+
+  it("rejects a Token-2022 mint with a permanent delegate", async () => {
+    const mintKp = anchor.web3.Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.PermanentDelegate]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mintKp.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializePermanentDelegateInstruction(
+        mintKp.publicKey,
+        payer.publicKey,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        mintKp.publicKey,
+        6,
+        payer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [mintKp]);
+
+    try {
+      await createRaffleWithMint(
+        "screen-permanent-delegate",
+        mintKp.publicKey,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintHasPermanentDelegate");
+    }
+  });
+
+  it("rejects a Token-2022 mint whose accounts start frozen", async () => {
+    const mintKp = anchor.web3.Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.DefaultAccountState]);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mintKp.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeDefaultAccountStateInstruction(
+        mintKp.publicKey,
+        AccountState.Frozen,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        mintKp.publicKey,
+        6,
+        payer.publicKey,
+        payer.publicKey,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [mintKp]);
+
+    try {
+      await createRaffleWithMint(
+        "screen-default-frozen",
+        mintKp.publicKey,
+        TOKEN_2022_PROGRAM_ID
+      );
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintDefaultFrozen");
+    }
+  });
+
+  it("still screens allowlisted mints", async () => {
+    const tokenMint = await createMint(
+      provider.connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6
+    );
+
+    await program.methods
+      .addAllowedMint(tokenMint)
+      .accounts({ config: configPda, admin: payer.publicKey })
+      .rpc();
+
+    try {
+      await createRaffleWithMint("screen-allowlisted", tokenMint, TOKEN_PROGRAM_ID);
+      expect.fail("Should have thrown");
+    } catch (e: any) {
+      expect(e.message).to.include("MintHasFreezeAuthority");
+    } finally {
+      await program.methods
+        .removeAllowedMint(tokenMint)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    }
+  });
+
+  it("accepts a screened-out mint once the admin exempts it", async () => {
+    const tokenMint = await createMint(
+      provider.connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6
+    );
+
+    await program.methods
+      .addScreenExemptMint(tokenMint)
+      .accounts({ config: configPda, admin: payer.publicKey })
+      .rpc();
+
+    try {
+      const rafflePda = await createRaffleWithMint(
+        "screen-exempt",
+        tokenMint,
+        TOKEN_PROGRAM_ID
+      );
+      const raffle = await program.account.raffle.fetch(rafflePda);
+      expect(raffle.tokenMint.toBase58()).to.equal(tokenMint.toBase58());
+    } finally {
+      await program.methods
+        .removeScreenExemptMint(tokenMint)
+        .accounts({ config: configPda, admin: payer.publicKey })
+        .rpc();
+    }
+  });
+});